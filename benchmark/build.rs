@@ -0,0 +1,4 @@
+// Re-embed the sqlx migrations whenever a migration file is added or changed.
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,53 @@
+-- Replaces the schema previously created inline by `PsqlClient::update_rune_pool`
+-- (SERIAL ids, no uniqueness). Existing rows are copied into the new tables: the
+-- latest meta row, and the latest interval for each start time.
+CREATE TABLE IF NOT EXISTS meta (
+    id SERIAL PRIMARY KEY,
+    start_time BIGINT NOT NULL,
+    end_time BIGINT NOT NULL,
+    start_count BIGINT NOT NULL,
+    end_count BIGINT NOT NULL,
+    start_units BIGINT NOT NULL,
+    end_units BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS intervals (
+    id SERIAL PRIMARY KEY,
+    start_time BIGINT NOT NULL,
+    end_time BIGINT NOT NULL,
+    count BIGINT NOT NULL,
+    units BIGINT NOT NULL
+);
+ALTER TABLE meta RENAME TO meta_legacy;
+ALTER TABLE meta_legacy RENAME CONSTRAINT meta_pkey TO meta_legacy_pkey;
+ALTER TABLE intervals RENAME TO intervals_legacy;
+ALTER TABLE intervals_legacy RENAME CONSTRAINT intervals_pkey TO intervals_legacy_pkey;
+
+CREATE TABLE meta (
+    start_time BIGINT PRIMARY KEY,
+    end_time BIGINT NOT NULL,
+    start_count BIGINT NOT NULL,
+    end_count BIGINT NOT NULL,
+    start_units BIGINT NOT NULL,
+    end_units BIGINT NOT NULL
+);
+
+CREATE TABLE intervals (
+    start_time BIGINT PRIMARY KEY,
+    end_time BIGINT NOT NULL,
+    count BIGINT NOT NULL,
+    units BIGINT NOT NULL,
+    CHECK (end_time > start_time)
+);
+
+CREATE INDEX intervals_end_time_idx ON intervals (end_time);
+
+INSERT INTO meta (start_time, end_time, start_count, end_count, start_units, end_units)
+SELECT start_time, end_time, start_count, end_count, start_units, end_units
+FROM meta_legacy ORDER BY id DESC LIMIT 1;
+
+INSERT INTO intervals (start_time, end_time, count, units)
+SELECT DISTINCT ON (start_time) start_time, end_time, count, units
+FROM intervals_legacy WHERE end_time > start_time ORDER BY start_time, id DESC;
+
+DROP TABLE meta_legacy;
+DROP TABLE intervals_legacy;
@@ -0,0 +1,74 @@
+-- One table per Midgard history series besides RUNEPool, with one column per field of
+-- its descriptor in `models::series`. `scope` is the pool for depths and empty for the
+-- protocol-wide series. A descriptor change needs a migration here; the client checks the
+-- columns against the descriptors at startup.
+
+CREATE TABLE history_depths (
+    dataset TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    start_time BIGINT NOT NULL,
+    end_time BIGINT NOT NULL,
+    asset_depth BIGINT NOT NULL,
+    rune_depth BIGINT NOT NULL,
+    asset_price DOUBLE PRECISION NOT NULL,
+    asset_price_usd DOUBLE PRECISION NOT NULL,
+    liquidity_units BIGINT NOT NULL,
+    members_count BIGINT NOT NULL,
+    synth_units BIGINT NOT NULL,
+    synth_supply BIGINT NOT NULL,
+    units BIGINT NOT NULL,
+    luvi DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (dataset, scope, start_time),
+    CHECK (end_time > start_time)
+);
+
+CREATE TABLE history_earnings (
+    dataset TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    start_time BIGINT NOT NULL,
+    end_time BIGINT NOT NULL,
+    liquidity_fees BIGINT NOT NULL,
+    block_rewards BIGINT NOT NULL,
+    earnings BIGINT NOT NULL,
+    bonding_earnings BIGINT NOT NULL,
+    liquidity_earnings BIGINT NOT NULL,
+    avg_node_count DOUBLE PRECISION NOT NULL,
+    rune_price_usd DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (dataset, scope, start_time),
+    CHECK (end_time > start_time)
+);
+
+CREATE TABLE history_swaps (
+    dataset TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    start_time BIGINT NOT NULL,
+    end_time BIGINT NOT NULL,
+    to_asset_count BIGINT NOT NULL,
+    to_rune_count BIGINT NOT NULL,
+    synth_mint_count BIGINT NOT NULL,
+    synth_redeem_count BIGINT NOT NULL,
+    total_count BIGINT NOT NULL,
+    to_asset_volume BIGINT NOT NULL,
+    to_rune_volume BIGINT NOT NULL,
+    synth_mint_volume BIGINT NOT NULL,
+    synth_redeem_volume BIGINT NOT NULL,
+    total_volume BIGINT NOT NULL,
+    total_fees BIGINT NOT NULL,
+    average_slip DOUBLE PRECISION NOT NULL,
+    rune_price_usd DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (dataset, scope, start_time),
+    CHECK (end_time > start_time)
+);
+
+CREATE TABLE history_tvl (
+    dataset TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    start_time BIGINT NOT NULL,
+    end_time BIGINT NOT NULL,
+    total_value_pooled BIGINT NOT NULL,
+    total_value_bonded BIGINT NOT NULL,
+    total_value_locked BIGINT NOT NULL,
+    rune_price_usd DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (dataset, scope, start_time),
+    CHECK (end_time > start_time)
+);
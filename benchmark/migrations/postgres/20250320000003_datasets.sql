@@ -0,0 +1,14 @@
+-- Named datasets: meta holds one row per dataset and intervals are keyed by dataset
+-- and start time. Rows already stored become the `default` dataset.
+ALTER TABLE meta ADD COLUMN dataset TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE meta ALTER COLUMN dataset DROP DEFAULT;
+ALTER TABLE meta DROP CONSTRAINT meta_pkey;
+ALTER TABLE meta ADD PRIMARY KEY (dataset);
+
+ALTER TABLE intervals ADD COLUMN dataset TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE intervals ALTER COLUMN dataset DROP DEFAULT;
+ALTER TABLE intervals DROP CONSTRAINT intervals_pkey;
+ALTER TABLE intervals ADD PRIMARY KEY (dataset, start_time);
+
+DROP INDEX intervals_end_time_idx;
+CREATE INDEX intervals_end_time_idx ON intervals (dataset, end_time);
@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS meta (
+    start_time INTEGER PRIMARY KEY,
+    end_time INTEGER NOT NULL,
+    start_count INTEGER NOT NULL,
+    end_count INTEGER NOT NULL,
+    start_units INTEGER NOT NULL,
+    end_units INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS intervals (
+    start_time INTEGER PRIMARY KEY,
+    end_time INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    units INTEGER NOT NULL,
+    CHECK (end_time > start_time)
+);
+
+CREATE INDEX IF NOT EXISTS intervals_end_time_idx ON intervals (end_time);
@@ -0,0 +1,74 @@
+-- One table per Midgard history series besides RUNEPool, with one column per field of
+-- its descriptor in `models::series`. `scope` is the pool for depths and empty for the
+-- protocol-wide series. A descriptor change needs a migration here; the client checks the
+-- columns against the descriptors at startup.
+
+CREATE TABLE IF NOT EXISTS history_depths (
+    dataset TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER NOT NULL,
+    asset_depth INTEGER NOT NULL,
+    rune_depth INTEGER NOT NULL,
+    asset_price REAL NOT NULL,
+    asset_price_usd REAL NOT NULL,
+    liquidity_units INTEGER NOT NULL,
+    members_count INTEGER NOT NULL,
+    synth_units INTEGER NOT NULL,
+    synth_supply INTEGER NOT NULL,
+    units INTEGER NOT NULL,
+    luvi REAL NOT NULL,
+    PRIMARY KEY (dataset, scope, start_time),
+    CHECK (end_time > start_time)
+);
+
+CREATE TABLE IF NOT EXISTS history_earnings (
+    dataset TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER NOT NULL,
+    liquidity_fees INTEGER NOT NULL,
+    block_rewards INTEGER NOT NULL,
+    earnings INTEGER NOT NULL,
+    bonding_earnings INTEGER NOT NULL,
+    liquidity_earnings INTEGER NOT NULL,
+    avg_node_count REAL NOT NULL,
+    rune_price_usd REAL NOT NULL,
+    PRIMARY KEY (dataset, scope, start_time),
+    CHECK (end_time > start_time)
+);
+
+CREATE TABLE IF NOT EXISTS history_swaps (
+    dataset TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER NOT NULL,
+    to_asset_count INTEGER NOT NULL,
+    to_rune_count INTEGER NOT NULL,
+    synth_mint_count INTEGER NOT NULL,
+    synth_redeem_count INTEGER NOT NULL,
+    total_count INTEGER NOT NULL,
+    to_asset_volume INTEGER NOT NULL,
+    to_rune_volume INTEGER NOT NULL,
+    synth_mint_volume INTEGER NOT NULL,
+    synth_redeem_volume INTEGER NOT NULL,
+    total_volume INTEGER NOT NULL,
+    total_fees INTEGER NOT NULL,
+    average_slip REAL NOT NULL,
+    rune_price_usd REAL NOT NULL,
+    PRIMARY KEY (dataset, scope, start_time),
+    CHECK (end_time > start_time)
+);
+
+CREATE TABLE IF NOT EXISTS history_tvl (
+    dataset TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER NOT NULL,
+    total_value_pooled INTEGER NOT NULL,
+    total_value_bonded INTEGER NOT NULL,
+    total_value_locked INTEGER NOT NULL,
+    rune_price_usd REAL NOT NULL,
+    PRIMARY KEY (dataset, scope, start_time),
+    CHECK (end_time > start_time)
+);
@@ -0,0 +1,33 @@
+-- Named datasets: meta holds one row per dataset and intervals are keyed by dataset
+-- and start time. Rows already stored become the `default` dataset. SQLite can't change
+-- a primary key in place, so each table is rebuilt and its rows copied across.
+ALTER TABLE meta RENAME TO meta_legacy;
+CREATE TABLE meta (
+    dataset TEXT PRIMARY KEY,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER NOT NULL,
+    start_count INTEGER NOT NULL,
+    end_count INTEGER NOT NULL,
+    start_units INTEGER NOT NULL,
+    end_units INTEGER NOT NULL
+);
+INSERT INTO meta (dataset, start_time, end_time, start_count, end_count, start_units, end_units)
+SELECT 'default', start_time, end_time, start_count, end_count, start_units, end_units FROM meta_legacy;
+DROP TABLE meta_legacy;
+
+DROP INDEX IF EXISTS intervals_end_time_idx;
+ALTER TABLE intervals RENAME TO intervals_legacy;
+CREATE TABLE intervals (
+    dataset TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    units INTEGER NOT NULL,
+    PRIMARY KEY (dataset, start_time),
+    CHECK (end_time > start_time)
+);
+INSERT INTO intervals (dataset, start_time, end_time, count, units)
+SELECT 'default', start_time, end_time, count, units FROM intervals_legacy;
+DROP TABLE intervals_legacy;
+
+CREATE INDEX intervals_end_time_idx ON intervals (dataset, end_time);
@@ -1,12 +1,25 @@
 use crate::config::Config;
+use crate::error::AppError;
+use crate::midgard::client::MidgardClient;
+use crate::db::dataset;
+use crate::db::duckdb::DuckDbClient;
+use crate::db::flatfile::FlatFileClient;
 use crate::db::leveldb::LevelDBClient;
+use crate::db::lmdb::LmdbClient;
+use crate::db::memory::MemoryClient;
 use crate::db::mongodb::MongoDBClient;
 use crate::db::psql::PsqlClient;
+use crate::db::redb::RedbClient;
+use crate::db::redis::RedisClient;
 use crate::db::rocksdb::RocksDBClient;
+use crate::db::sled::SledClient;
+use crate::db::sqlite::SqliteClient;
 use crate::db::surrealdb::SurrealDBClient;
-use crate::models::rune_pool::{ApiRunePoolResponse, DbRunePoolResponse};
+use crate::models::rune_pool::{ApiInterval, ApiRunePoolResponse, DbRunePoolResponse};
+use crate::models::series::{self, Record, Schema};
+use crate::models::validate;
+use crate::models::verify;
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use reqwest::Client as HttpClient;
 use serde_json::json;
 use std::collections::HashMap;
 use std::error::Error;
@@ -17,185 +30,828 @@ use tokio::sync::Mutex;
 #[derive(Clone)]
 pub struct AppState {
     config: Config,
+    memory: Arc<MemoryClient>,
     leveldb: Arc<LevelDBClient>,
     rocksdb: Arc<RocksDBClient>,
+    sled: Arc<SledClient>,
+    redb: Arc<RedbClient>,
+    lmdb: Arc<LmdbClient>,
+    flatfile: Arc<FlatFileClient>,
     surrealdb: Arc<Mutex<SurrealDBClient>>,
     psql: Arc<Mutex<PsqlClient>>,
+    sqlite: Arc<Mutex<SqliteClient>>,
+    duckdb: Arc<DuckDbClient>,
     mongodb: Arc<Mutex<MongoDBClient>>,
-    http_client: HttpClient,
+    redis: Arc<Mutex<RedisClient>>,
+    midgard: Arc<MidgardClient>,
 }
 
 impl AppState {
     pub async fn new(config: Config) -> Result<Self, Box<dyn Error>> {
+        let memory = Arc::new(MemoryClient::default());
         let leveldb = Arc::new(LevelDBClient::new(&config)?);
         let rocksdb = Arc::new(RocksDBClient::new(&config)?);
+        let sled = Arc::new(SledClient::new(&config)?);
+        let redb = Arc::new(RedbClient::new(&config)?);
+        let lmdb = Arc::new(LmdbClient::new(&config)?);
+        let flatfile = Arc::new(FlatFileClient::new(&config)?);
         let surrealdb = Arc::new(Mutex::new(SurrealDBClient::new(&config).await?));
         let psql = Arc::new(Mutex::new(PsqlClient::new(&config).await?));
+        let sqlite = Arc::new(Mutex::new(SqliteClient::new(&config).await?));
+        let duckdb = Arc::new(DuckDbClient::new(&config)?);
         let mongodb = Arc::new(Mutex::new(MongoDBClient::new(&config).await?));
-        let http_client = HttpClient::new();
+        let redis = Arc::new(Mutex::new(RedisClient::new(&config).await?));
+        let midgard = Arc::new(MidgardClient::new(&config)?);
 
         Ok(AppState {
             config,
+            memory,
             leveldb,
             rocksdb,
+            sled,
+            redb,
+            lmdb,
+            flatfile,
             surrealdb,
             psql,
+            sqlite,
+            duckdb,
             mongodb,
-            http_client,
+            redis,
+            midgard,
         })
     }
 }
 
+fn check_payload(payload: &ApiRunePoolResponse, source: &str) -> Result<(), AppError> {
+    let violations = validate::validate(payload);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Invalid { source: source.to_string(), violations })
+    }
+}
+
 pub async fn update_rune_pool(
     State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
     Json(payload): Json<ApiRunePoolResponse>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, AppError> {
+    let dataset = dataset_param(&params, &state.config)?;
+    check_payload(&payload, "request")?;
+
     let db_response: DbRunePoolResponse = payload.clone().into();
     let mut timings = HashMap::new();
 
     let start = Instant::now();
-    state.leveldb.update_rune_pool(&db_response).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.memory.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("memory", e))?;
+    timings.insert("memory", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.leveldb.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("leveldb", e))?;
     timings.insert("leveldb", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.rocksdb.update_rune_pool(&db_response).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.rocksdb.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("rocksdb", e))?;
     timings.insert("rocksdb", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.surrealdb.lock().await.update_rune_pool(&db_response).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.sled.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("sled", e))?;
+    timings.insert("sled", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.redb.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("redb", e))?;
+    timings.insert("redb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.lmdb.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("lmdb", e))?;
+    timings.insert("lmdb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.flatfile.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("flatfile", e))?;
+    timings.insert("flatfile", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.surrealdb.lock().await.update_rune_pool(dataset, &db_response).await.map_err(|e| AppError::backend("surrealdb", e))?;
     timings.insert("surrealdb", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.psql.lock().await.update_rune_pool(&db_response).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.psql.lock().await.update_rune_pool(dataset, &db_response).await.map_err(|e| AppError::backend("psql", e))?;
     timings.insert("psql", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.mongodb.lock().await.update_rune_pool(&db_response).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.sqlite.lock().await.update_rune_pool(dataset, &db_response).await.map_err(|e| AppError::backend("sqlite", e))?;
+    timings.insert("sqlite", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.duckdb.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("duckdb", e))?;
+    timings.insert("duckdb", start.elapsed().as_millis());
+
+    let mongodb = state.mongodb.lock().await;
+    let start = Instant::now();
+    mongodb.update_rune_pool(dataset, &db_response).await.map_err(|e| AppError::backend("mongodb", e))?;
     timings.insert("mongodb", start.elapsed().as_millis());
+    let mongodb_mode = mongodb.write_mode();
+    drop(mongodb);
+
+    let start = Instant::now();
+    state.redis.lock().await.update_rune_pool(dataset, &db_response).await.map_err(|e| AppError::backend("redis", e))?;
+    timings.insert("redis", start.elapsed().as_millis());
 
     Ok((StatusCode::OK, Json(json!({
+        "dataset": dataset,
         "data": payload,
-        "timings": timings
+        "timings": timings,
+        "modes": {
+            "mongodb": mongodb_mode,
+            "rocksdb": state.rocksdb.settings(),
+            "kv_codec": state.config.kv_codec
+        }
     }))))
 }
 
+/// Every backend `read_rune_pool` can serve, in the order reports list them.
+const BACKENDS: &[&str] = &[
+    "memory", "leveldb", "rocksdb", "sled", "redb", "lmdb", "flatfile", "surrealdb", "psql", "duckdb", "sqlite",
+    "mongodb", "redis",
+];
+
+/// Reads the full dataset from the backend named by `db`.
+async fn read_rune_pool(
+    state: &AppState,
+    db: &str,
+    params: &HashMap<String, String>,
+) -> Result<DbRunePoolResponse, AppError> {
+    let dataset = dataset_param(params, &state.config)?;
+    match db {
+        "memory" => state.memory.get_rune_pool(dataset).map_err(|e| AppError::backend("memory", e)),
+        "leveldb" => state.leveldb.get_rune_pool(dataset).map_err(|e| AppError::backend("leveldb", e)),
+        "rocksdb" => state.rocksdb.get_rune_pool(dataset).map_err(|e| AppError::backend("rocksdb", e)),
+        "sled" => state.sled.get_rune_pool(dataset).map_err(|e| AppError::backend("sled", e)),
+        "redb" => state.redb.get_rune_pool(dataset).map_err(|e| AppError::backend("redb", e)),
+        "lmdb" => state.lmdb.get_rune_pool(dataset).map_err(|e| AppError::backend("lmdb", e)),
+        "flatfile" => state.flatfile.get_rune_pool(dataset).map_err(|e| AppError::backend("flatfile", e)),
+        "surrealdb" => {
+            let ns = params.get("ns").map(String::as_str);
+            state.surrealdb.lock().await.get_rune_pool(dataset, ns).await.map_err(|e| AppError::backend("surrealdb", e))
+        }
+        "psql" => state.psql.lock().await.get_rune_pool(dataset).await.map_err(|e| AppError::backend("psql", e)),
+        "duckdb" => state.duckdb.get_rune_pool(dataset).map_err(|e| AppError::backend("duckdb", e)),
+        "sqlite" => state.sqlite.lock().await.get_rune_pool(dataset).await.map_err(|e| AppError::backend("sqlite", e)),
+        "mongodb" => state.mongodb.lock().await.get_rune_pool(dataset).await.map_err(|e| AppError::backend("mongodb", e)),
+        "redis" => state.redis.lock().await.get_rune_pool(dataset).await.map_err(|e| AppError::backend("redis", e)),
+        _ => Err(AppError::BadRequest(format!("Unknown database: {}", db))),
+    }
+}
+
+/// The dataset named by `dataset`, or the configured default when the request names none.
+fn dataset_param<'a>(params: &'a HashMap<String, String>, config: &'a Config) -> Result<&'a str, AppError> {
+    let name = params.get("dataset").unwrap_or(&config.dataset);
+    dataset::check_name(name).map_err(|e| AppError::BadRequest(e.to_string()))?;
+    Ok(name)
+}
+
+fn db_param(params: &HashMap<String, String>) -> Result<&String, AppError> {
+    params
+        .get("db")
+        .ok_or(AppError::BadRequest("Missing 'db' query parameter".to_string()))
+}
+
 pub async fn get_rune_pool(
     State(state): State<AppState>,
     axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let db = params.get("db").ok_or((
-        StatusCode::BAD_REQUEST,
-        "Missing 'db' query parameter".to_string(),
-    ))?;
-
-    let (retrieved_api, timing): (ApiRunePoolResponse, u128) = match db.as_str() {
-        "leveldb" => {
-            let start = Instant::now();
-            let retrieved_db = state.leveldb.get_rune_pool().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-            (retrieved_db.into(), start.elapsed().as_millis())
-        }
-        "rocksdb" => {
-            let start = Instant::now();
-            let retrieved_db = state.rocksdb.get_rune_pool().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-            (retrieved_db.into(), start.elapsed().as_millis())
-        }
-        "surrealdb" => {
-            let start = Instant::now();
-            let retrieved_db = state.surrealdb.lock().await.get_rune_pool().await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-            (retrieved_db.into(), start.elapsed().as_millis())
-        }
-        "psql" => {
-            let start = Instant::now();
-            let retrieved_db = state.psql.lock().await.get_rune_pool().await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-            (retrieved_db.into(), start.elapsed().as_millis())
-        }
-        "mongodb" => {
-            let start = Instant::now();
-            let retrieved_db = state.mongodb.lock().await.get_rune_pool().await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-            (retrieved_db.into(), start.elapsed().as_millis())
-        }
-        _ => return Err((StatusCode::BAD_REQUEST, format!("Unknown database: {}", db))),
-    };
+) -> Result<impl IntoResponse, AppError> {
+    let db = db_param(&params)?;
+    let dataset = dataset_param(&params, &state.config)?;
+
+    let start = Instant::now();
+    let retrieved_db = read_rune_pool(&state, db, &params).await?;
+    let timing = start.elapsed().as_millis();
+    let retrieved_api: ApiRunePoolResponse = retrieved_db.into();
 
     Ok((StatusCode::OK, Json(json!({
+        "dataset": dataset,
         "data": retrieved_api,
         "timing": timing
     }))))
 }
 
+/// Buckets intervals into `seconds`-wide windows. DuckDB aggregates in SQL; every
+/// other backend is read in full and aggregated in Rust.
+pub async fn resample_rune_pool(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let db = db_param(&params)?;
+    let seconds = params
+        .get("seconds")
+        .ok_or(AppError::BadRequest("Missing 'seconds' query parameter".to_string()))?
+        .parse::<u64>()
+        .ok()
+        .filter(|seconds| *seconds > 0)
+        .ok_or(AppError::BadRequest("'seconds' must be a positive integer".to_string()))?;
+
+    let dataset = dataset_param(&params, &state.config)?;
+
+    let start = Instant::now();
+    let resampled = if db == "duckdb" {
+        state.duckdb.resample(dataset, seconds).map_err(|e| AppError::backend("duckdb", e))?
+    } else {
+        read_rune_pool(&state, db, &params).await?.resample(seconds)
+    };
+    let timing = start.elapsed().as_millis();
+    let resampled_api: ApiRunePoolResponse = resampled.into();
+
+    Ok((StatusCode::OK, Json(json!({
+        "dataset": dataset,
+        "data": resampled_api,
+        "timing": timing
+    }))))
+}
+
+fn u64_param(params: &HashMap<String, String>, name: &str) -> Result<u64, AppError> {
+    params
+        .get(name)
+        .ok_or(AppError::BadRequest(format!("Missing '{}' query parameter", name)))?
+        .parse::<u64>()
+        .map_err(|_| AppError::BadRequest(format!("'{}' must be an unsigned integer", name)))
+}
+
+/// Intervals with `from <= start_time < to`. The flat file seeks through its sparse
+/// index and Redis selects by score; every other backend is read in full and filtered in Rust.
+pub async fn get_range(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let db = db_param(&params)?;
+    let from = u64_param(&params, "from")?;
+    let to = u64_param(&params, "to")?;
+
+    let dataset = dataset_param(&params, &state.config)?;
+
+    let start = Instant::now();
+    let range = if db == "flatfile" {
+        state.flatfile.get_range(dataset, from, to).map_err(|e| AppError::backend("flatfile", e))?
+    } else if db == "redis" {
+        state.redis.lock().await.get_range(dataset, from, to).await.map_err(|e| AppError::backend("redis", e))?
+    } else {
+        read_rune_pool(&state, db, &params).await?.range(from, to)
+    };
+    let timing = start.elapsed().as_millis();
+    let range_api: ApiRunePoolResponse = range.into();
+
+    Ok((StatusCode::OK, Json(json!({
+        "dataset": dataset,
+        "data": range_api,
+        "timing": timing
+    }))))
+}
+
+/// The most recent interval. The flat file reads only its last record.
+pub async fn get_latest(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let db = db_param(&params)?;
+    let dataset = dataset_param(&params, &state.config)?;
+
+    let start = Instant::now();
+    let latest = if db == "flatfile" {
+        state.flatfile.get_latest(dataset).map_err(|e| AppError::backend("flatfile", e))?
+    } else {
+        read_rune_pool(&state, db, &params).await?.intervals.pop()
+    };
+    let timing = start.elapsed().as_millis();
+    let latest_api = latest.map(|interval| ApiInterval {
+        start_time: interval.start_time,
+        end_time: interval.end_time,
+        count: interval.count,
+        units: interval.units,
+    });
+
+    Ok((StatusCode::OK, Json(json!({
+        "dataset": dataset,
+        "data": latest_api,
+        "timing": timing
+    }))))
+}
+
+/// Stores whose size is computed from the data rather than measured on disk or by the server.
+const ESTIMATED_SIZES: [&str; 2] = ["memory", "surrealdb"];
+
+/// Bytes and rows held by each store, and bytes per row. Rows are the store's own interval
+/// and history records across every dataset, counted from its data, so a store that lost or
+/// duplicated writes shows it. Stores hold all datasets in the same files and tables, so sizes
+/// aren't split per dataset; `datasets` lists each one's interval count in the reference
+/// instead. `estimated` marks the stores in `ESTIMATED_SIZES`.
+async fn storage_footprint(state: &AppState) -> Result<serde_json::Value, AppError> {
+    let mut sizes = HashMap::new();
+    sizes.insert("memory", state.memory.storage_size().map_err(|e| AppError::backend("memory", e))?);
+    sizes.insert("leveldb", state.leveldb.storage_size().map_err(|e| AppError::backend("leveldb", e))?);
+    sizes.insert("rocksdb", state.rocksdb.storage_size().map_err(|e| AppError::backend("rocksdb", e))?);
+    sizes.insert("sled", state.sled.storage_size().map_err(|e| AppError::backend("sled", e))?);
+    sizes.insert("redb", state.redb.storage_size().map_err(|e| AppError::backend("redb", e))?);
+    sizes.insert("lmdb", state.lmdb.storage_size().map_err(|e| AppError::backend("lmdb", e))?);
+    sizes.insert("flatfile", state.flatfile.storage_size().map_err(|e| AppError::backend("flatfile", e))?);
+    sizes.insert("duckdb", state.duckdb.storage_size().map_err(|e| AppError::backend("duckdb", e))?);
+    sizes.insert("surrealdb", state.surrealdb.lock().await.storage_size().await.map_err(|e| AppError::backend("surrealdb", e))?);
+    sizes.insert("psql", state.psql.lock().await.storage_size().await.map_err(|e| AppError::backend("psql", e))?);
+    sizes.insert("sqlite", state.sqlite.lock().await.storage_size().await.map_err(|e| AppError::backend("sqlite", e))?);
+    sizes.insert("mongodb", state.mongodb.lock().await.storage_size().await.map_err(|e| AppError::backend("mongodb", e))?);
+    sizes.insert("redis", state.redis.lock().await.storage_size().await.map_err(|e| AppError::backend("redis", e))?);
+
+    let mut rows = HashMap::new();
+    rows.insert("memory", state.memory.row_count().map_err(|e| AppError::backend("memory", e))?);
+    rows.insert("leveldb", state.leveldb.row_count().map_err(|e| AppError::backend("leveldb", e))?);
+    rows.insert("rocksdb", state.rocksdb.row_count().map_err(|e| AppError::backend("rocksdb", e))?);
+    rows.insert("sled", state.sled.row_count().map_err(|e| AppError::backend("sled", e))?);
+    rows.insert("redb", state.redb.row_count().map_err(|e| AppError::backend("redb", e))?);
+    rows.insert("lmdb", state.lmdb.row_count().map_err(|e| AppError::backend("lmdb", e))?);
+    rows.insert("flatfile", state.flatfile.row_count().map_err(|e| AppError::backend("flatfile", e))?);
+    rows.insert("duckdb", state.duckdb.row_count().map_err(|e| AppError::backend("duckdb", e))?);
+    rows.insert("surrealdb", state.surrealdb.lock().await.row_count().await.map_err(|e| AppError::backend("surrealdb", e))?);
+    rows.insert("psql", state.psql.lock().await.row_count().await.map_err(|e| AppError::backend("psql", e))?);
+    rows.insert("sqlite", state.sqlite.lock().await.row_count().await.map_err(|e| AppError::backend("sqlite", e))?);
+    rows.insert("mongodb", state.mongodb.lock().await.row_count().await.map_err(|e| AppError::backend("mongodb", e))?);
+    rows.insert("redis", state.redis.lock().await.row_count().await.map_err(|e| AppError::backend("redis", e))?);
+
+    let datasets = state.memory.dataset_intervals().map_err(|e| AppError::backend("memory", e))?;
+    let stores: HashMap<_, _> = sizes
+        .into_iter()
+        .map(|(name, bytes)| {
+            let rows = rows[name];
+            let per_row = (rows > 0).then(|| bytes as f64 / rows as f64);
+            let estimated = ESTIMATED_SIZES.contains(&name);
+            (name, json!({ "bytes": bytes, "rows": rows, "bytes_per_row": per_row, "estimated": estimated }))
+        })
+        .collect();
+
+    Ok(json!({ "datasets": datasets, "stores": stores }))
+}
+
+/// Storage footprint of every store. Only served here, so counting rows never slows down or
+/// fails an `/update` whose writes already succeeded.
+pub async fn get_stats(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let footprint = storage_footprint(&state).await?;
+    Ok((StatusCode::OK, Json(footprint)))
+}
+
+/// Compares every backend against `reference`. Returns whether all of them matched,
+/// alongside the per-backend report; backends that fail to read are reported as errors.
+pub async fn verify_backends(
+    state: &AppState,
+    reference: &str,
+    params: &HashMap<String, String>,
+) -> Result<(bool, serde_json::Value), AppError> {
+    let expected = read_rune_pool(state, reference, params).await?;
+    let mut consistent = true;
+    let mut backends = serde_json::Map::new();
+
+    for &db in BACKENDS.iter().filter(|&&db| db != reference) {
+        let report = match read_rune_pool(state, db, params).await {
+            Ok(actual) => {
+                let discrepancies = verify::compare(&expected, &actual);
+                consistent &= discrepancies.is_consistent();
+                json!({ "consistent": discrepancies.is_consistent(), "discrepancies": discrepancies })
+            }
+            Err(e) => {
+                consistent = false;
+                json!({ "consistent": false, "error": e.to_string() })
+            }
+        };
+        backends.insert(db.to_string(), report);
+    }
+
+    Ok((consistent, json!({
+        "reference": reference,
+        "dataset": dataset_param(params, &state.config)?,
+        "intervals": expected.intervals.len(),
+        "consistent": consistent,
+        "backends": backends
+    })))
+}
+
+/// Checks that every backend holds the same data as `reference` (the in-process memory store by default).
+pub async fn verify(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let reference = params.get("reference").map(String::as_str).unwrap_or("memory");
+    let (_, report) = verify_backends(&state, reference, &params).await?;
+    Ok((StatusCode::OK, Json(report)))
+}
+
+/// Writes the dataset to Parquet under `PARQUET_DIR`. The directory is fixed by config so
+/// requests can't write anywhere else on the host.
+pub async fn export_parquet(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let dir = &state.config.parquet_dir;
+    let dataset = dataset_param(&params, &state.config)?;
+
+    let start = Instant::now();
+    state.duckdb.export_parquet(dataset, dir).map_err(|e| AppError::backend("duckdb", e))?;
+    let timing = start.elapsed().as_millis();
+
+    Ok((StatusCode::OK, Json(json!({ "dataset": dataset, "dir": dir, "timing": timing }))))
+}
+
+/// Replaces the dataset with the Parquet files under `PARQUET_DIR`.
+pub async fn import_parquet(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let dir = &state.config.parquet_dir;
+    let dataset = dataset_param(&params, &state.config)?;
+
+    let start = Instant::now();
+    state.duckdb.import_parquet(dataset, dir).map_err(|e| AppError::backend("duckdb", e))?;
+    let timing = start.elapsed().as_millis();
+
+    Ok((StatusCode::OK, Json(json!({ "dataset": dataset, "dir": dir, "timing": timing }))))
+}
+
 pub async fn fetch_and_update_rune_pool(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let url = format!(
-        "{}?interval={}&from={}&count=400",
-        state.config.api_url,
-        state.config.interval,
-        state.config.initial_from
-    );
-
-    let response = state
-        .http_client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch from Midgard: {}", e)))?
-        .json::<ApiRunePoolResponse>()
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let dataset = dataset_param(&params, &state.config)?;
+    let midgard_params = [
+        ("interval", state.config.interval.clone()),
+        ("from", state.config.initial_from.to_string()),
+        ("count", "400".to_string()),
+    ];
+    let (response, fetch_report) = state
+        .midgard
+        .runepool_history(&state.config.api_url, &midgard_params)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse Midgard response: {}", e)))?;
+        .map_err(|e| AppError::Upstream(format!("Failed to fetch from Midgard: {}", e)))?;
+
+    check_payload(&response, "Midgard")?;
 
     let db_response: DbRunePoolResponse = response.clone().into();
     let mut timings = HashMap::new();
 
     let start = Instant::now();
-    state.leveldb.update_rune_pool(&db_response).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.memory.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("memory", e))?;
+    timings.insert("memory", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.leveldb.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("leveldb", e))?;
     timings.insert("leveldb", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.rocksdb.update_rune_pool(&db_response).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.rocksdb.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("rocksdb", e))?;
     timings.insert("rocksdb", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.surrealdb.lock().await.update_rune_pool(&db_response).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.sled.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("sled", e))?;
+    timings.insert("sled", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.redb.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("redb", e))?;
+    timings.insert("redb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.lmdb.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("lmdb", e))?;
+    timings.insert("lmdb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.flatfile.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("flatfile", e))?;
+    timings.insert("flatfile", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.surrealdb.lock().await.update_rune_pool(dataset, &db_response).await.map_err(|e| AppError::backend("surrealdb", e))?;
     timings.insert("surrealdb", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.psql.lock().await.update_rune_pool(&db_response).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.psql.lock().await.update_rune_pool(dataset, &db_response).await.map_err(|e| AppError::backend("psql", e))?;
     timings.insert("psql", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.mongodb.lock().await.update_rune_pool(&db_response).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.sqlite.lock().await.update_rune_pool(dataset, &db_response).await.map_err(|e| AppError::backend("sqlite", e))?;
+    timings.insert("sqlite", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.duckdb.update_rune_pool(dataset, &db_response).map_err(|e| AppError::backend("duckdb", e))?;
+    timings.insert("duckdb", start.elapsed().as_millis());
+
+    let mongodb = state.mongodb.lock().await;
+    let start = Instant::now();
+    mongodb.update_rune_pool(dataset, &db_response).await.map_err(|e| AppError::backend("mongodb", e))?;
     timings.insert("mongodb", start.elapsed().as_millis());
+    let mongodb_mode = mongodb.write_mode();
+    drop(mongodb);
+
+    let start = Instant::now();
+    state.redis.lock().await.update_rune_pool(dataset, &db_response).await.map_err(|e| AppError::backend("redis", e))?;
+    timings.insert("redis", start.elapsed().as_millis());
 
     Ok((StatusCode::OK, Json(json!({
+        "dataset": dataset,
         "data": response,
-        "timings": timings
+        "timings": timings,
+        "modes": {
+            "mongodb": mongodb_mode,
+            "rocksdb": state.rocksdb.settings(),
+            "kv_codec": state.config.kv_codec
+        },
+        "midgard": fetch_report
     }))))
 }
 
+/// The series named by `series`, and its scope: the `pool` for depths, empty otherwise.
+fn series_params(params: &HashMap<String, String>) -> Result<(&'static Schema, String), AppError> {
+    let series = params
+        .get("series")
+        .ok_or(AppError::BadRequest("Missing 'series' query parameter".to_string()))?;
+    let schema = series::find(series).map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let scope = match params.get("pool") {
+        Some(pool) if schema.needs_pool() => {
+            series::check_pool(pool).map_err(|e| AppError::BadRequest(e.to_string()))?;
+            pool.clone()
+        }
+        None if schema.needs_pool() => {
+            return Err(AppError::BadRequest(format!("Series '{}' needs a 'pool' query parameter", series)))
+        }
+        _ => String::new(),
+    };
+    Ok((schema, scope))
+}
+
+/// Replaces one history series in every backend, returning per-backend timings.
+async fn write_history(
+    state: &AppState,
+    schema: &Schema,
+    dataset: &str,
+    scope: &str,
+    records: &[Record],
+) -> Result<HashMap<&'static str, u128>, AppError> {
+    let mut timings = HashMap::new();
+
+    let start = Instant::now();
+    state.memory.update_series(schema, dataset, scope, records).map_err(|e| AppError::backend("memory", e))?;
+    timings.insert("memory", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.leveldb.update_series(schema, dataset, scope, records).map_err(|e| AppError::backend("leveldb", e))?;
+    timings.insert("leveldb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.rocksdb.update_series(schema, dataset, scope, records).map_err(|e| AppError::backend("rocksdb", e))?;
+    timings.insert("rocksdb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.sled.update_series(schema, dataset, scope, records).map_err(|e| AppError::backend("sled", e))?;
+    timings.insert("sled", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.redb.update_series(schema, dataset, scope, records).map_err(|e| AppError::backend("redb", e))?;
+    timings.insert("redb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.lmdb.update_series(schema, dataset, scope, records).map_err(|e| AppError::backend("lmdb", e))?;
+    timings.insert("lmdb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.flatfile.update_series(schema, dataset, scope, records).map_err(|e| AppError::backend("flatfile", e))?;
+    timings.insert("flatfile", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.surrealdb.lock().await.update_series(schema, dataset, scope, records).await.map_err(|e| AppError::backend("surrealdb", e))?;
+    timings.insert("surrealdb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.psql.lock().await.update_series(schema, dataset, scope, records).await.map_err(|e| AppError::backend("psql", e))?;
+    timings.insert("psql", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.sqlite.lock().await.update_series(schema, dataset, scope, records).await.map_err(|e| AppError::backend("sqlite", e))?;
+    timings.insert("sqlite", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.duckdb.update_series(schema, dataset, scope, records).map_err(|e| AppError::backend("duckdb", e))?;
+    timings.insert("duckdb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.mongodb.lock().await.update_series(schema, dataset, scope, records).await.map_err(|e| AppError::backend("mongodb", e))?;
+    timings.insert("mongodb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    state.redis.lock().await.update_series(schema, dataset, scope, records).await.map_err(|e| AppError::backend("redis", e))?;
+    timings.insert("redis", start.elapsed().as_millis());
+
+    Ok(timings)
+}
+
+/// Reads one history series from the backend named by `db`.
+async fn read_history(
+    state: &AppState,
+    db: &str,
+    schema: &Schema,
+    scope: &str,
+    params: &HashMap<String, String>,
+) -> Result<Vec<Record>, AppError> {
+    let dataset = dataset_param(params, &state.config)?;
+    match db {
+        "memory" => state.memory.get_series(schema, dataset, scope).map_err(|e| AppError::backend("memory", e)),
+        "leveldb" => state.leveldb.get_series(schema, dataset, scope).map_err(|e| AppError::backend("leveldb", e)),
+        "rocksdb" => state.rocksdb.get_series(schema, dataset, scope).map_err(|e| AppError::backend("rocksdb", e)),
+        "sled" => state.sled.get_series(schema, dataset, scope).map_err(|e| AppError::backend("sled", e)),
+        "redb" => state.redb.get_series(schema, dataset, scope).map_err(|e| AppError::backend("redb", e)),
+        "lmdb" => state.lmdb.get_series(schema, dataset, scope).map_err(|e| AppError::backend("lmdb", e)),
+        "flatfile" => state.flatfile.get_series(schema, dataset, scope).map_err(|e| AppError::backend("flatfile", e)),
+        "surrealdb" => {
+            let ns = params.get("ns").map(String::as_str);
+            state.surrealdb.lock().await.get_series(schema, dataset, scope, ns).await.map_err(|e| AppError::backend("surrealdb", e))
+        }
+        "psql" => state.psql.lock().await.get_series(schema, dataset, scope).await.map_err(|e| AppError::backend("psql", e)),
+        "duckdb" => state.duckdb.get_series(schema, dataset, scope).map_err(|e| AppError::backend("duckdb", e)),
+        "sqlite" => state.sqlite.lock().await.get_series(schema, dataset, scope).await.map_err(|e| AppError::backend("sqlite", e)),
+        "mongodb" => state.mongodb.lock().await.get_series(schema, dataset, scope).await.map_err(|e| AppError::backend("mongodb", e)),
+        "redis" => state.redis.lock().await.get_series(schema, dataset, scope).await.map_err(|e| AppError::backend("redis", e)),
+        _ => Err(AppError::BadRequest(format!("Unknown database: {}", db))),
+    }
+}
+
+/// Fetches `series` (plus `pool` for depths) from Midgard and stores it in every backend.
+pub async fn fetch_history(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let (schema, scope) = series_params(&params)?;
+    let dataset = dataset_param(&params, &state.config)?;
+    let url = format!("{}/{}", state.config.midgard_base_url.trim_end_matches('/'), schema.path(&scope));
+    let midgard_params = [
+        ("interval", state.config.interval.clone()),
+        ("from", state.config.initial_from.to_string()),
+        ("count", "400".to_string()),
+    ];
+    let (body, fetch_report) = state
+        .midgard
+        .get_raw(&url, &midgard_params)
+        .await
+        .map_err(|e| AppError::Upstream(format!("Failed to fetch from Midgard: {}", e)))?;
+    let records = schema
+        .decode(&body)
+        .map_err(|e| AppError::Upstream(format!("Unparseable {} history from Midgard: {}", schema.name, e)))?;
+
+    let violations = validate::validate_records(schema, &records);
+    if !violations.is_empty() {
+        return Err(AppError::Invalid { source: "Midgard".to_string(), violations });
+    }
+
+    let timings = write_history(&state, schema, dataset, &scope, &records).await?;
+
+    Ok((StatusCode::OK, Json(json!({
+        "dataset": dataset,
+        "series": schema.name,
+        "pool": scope,
+        "intervals": records.len(),
+        "timings": timings,
+        "midgard": fetch_report
+    }))))
+}
+
+pub async fn get_history(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let db = db_param(&params)?;
+    let (schema, scope) = series_params(&params)?;
+    let dataset = dataset_param(&params, &state.config)?;
+
+    let start = Instant::now();
+    let records = read_history(&state, db, schema, &scope, &params).await?;
+    let timing = start.elapsed().as_millis();
+
+    let intervals: Vec<serde_json::Value> = records.iter().map(|record| schema.to_json(record)).collect();
+
+    Ok((StatusCode::OK, Json(json!({
+        "dataset": dataset,
+        "data": { "intervals": intervals },
+        "timing": timing
+    }))))
+}
+
+/// Deletes one dataset, `dataset` or `DATASET`, from every backend, history included.
+/// `all=true` wipes every dataset instead.
 pub async fn clear_databases(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let all = match params.get("all").map(String::as_str) {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(other) => return Err(AppError::BadRequest(format!("'all' must be true or false, not '{}'", other))),
+    };
+    let dataset = if all { None } else { Some(dataset_param(&params, &state.config)?) };
     let mut timings = HashMap::new();
 
     let start = Instant::now();
-    state.leveldb.clear().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match dataset {
+        Some(name) => state.memory.clear_dataset(name),
+        None => state.memory.clear(),
+    }
+    .map_err(|e| AppError::backend("memory", e))?;
+    timings.insert("memory", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    match dataset {
+        Some(name) => state.leveldb.clear_dataset(name),
+        None => state.leveldb.clear(),
+    }
+    .map_err(|e| AppError::backend("leveldb", e))?;
     timings.insert("leveldb", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.rocksdb.clear().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match dataset {
+        Some(name) => state.rocksdb.clear_dataset(name),
+        None => state.rocksdb.clear(),
+    }
+    .map_err(|e| AppError::backend("rocksdb", e))?;
     timings.insert("rocksdb", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.surrealdb.lock().await.clear().await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match dataset {
+        Some(name) => state.sled.clear_dataset(name),
+        None => state.sled.clear(),
+    }
+    .map_err(|e| AppError::backend("sled", e))?;
+    timings.insert("sled", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    match dataset {
+        Some(name) => state.redb.clear_dataset(name),
+        None => state.redb.clear(),
+    }
+    .map_err(|e| AppError::backend("redb", e))?;
+    timings.insert("redb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    match dataset {
+        Some(name) => state.lmdb.clear_dataset(name),
+        None => state.lmdb.clear(),
+    }
+    .map_err(|e| AppError::backend("lmdb", e))?;
+    timings.insert("lmdb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    match dataset {
+        Some(name) => state.flatfile.clear_dataset(name),
+        None => state.flatfile.clear(),
+    }
+    .map_err(|e| AppError::backend("flatfile", e))?;
+    timings.insert("flatfile", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    match dataset {
+        Some(name) => state.surrealdb.lock().await.clear_dataset(name).await,
+        None => state.surrealdb.lock().await.clear().await,
+    }
+    .map_err(|e| AppError::backend("surrealdb", e))?;
     timings.insert("surrealdb", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.psql.lock().await.clear().await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match dataset {
+        Some(name) => state.psql.lock().await.clear_dataset(name).await,
+        None => state.psql.lock().await.clear().await,
+    }
+    .map_err(|e| AppError::backend("psql", e))?;
     timings.insert("psql", start.elapsed().as_millis());
 
     let start = Instant::now();
-    state.mongodb.lock().await.clear().await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match dataset {
+        Some(name) => state.sqlite.lock().await.clear_dataset(name).await,
+        None => state.sqlite.lock().await.clear().await,
+    }
+    .map_err(|e| AppError::backend("sqlite", e))?;
+    timings.insert("sqlite", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    match dataset {
+        Some(name) => state.duckdb.clear_dataset(name),
+        None => state.duckdb.clear(),
+    }
+    .map_err(|e| AppError::backend("duckdb", e))?;
+    timings.insert("duckdb", start.elapsed().as_millis());
+
+    let start = Instant::now();
+    match dataset {
+        Some(name) => state.mongodb.lock().await.clear_dataset(name).await,
+        None => state.mongodb.lock().await.clear().await,
+    }
+    .map_err(|e| AppError::backend("mongodb", e))?;
     timings.insert("mongodb", start.elapsed().as_millis());
 
-    Ok((StatusCode::OK, Json(json!({ "timings": timings })))) // Changed to 200 OK with timings
+    let start = Instant::now();
+    match dataset {
+        Some(name) => state.redis.lock().await.clear_dataset(name).await,
+        None => state.redis.lock().await.clear().await,
+    }
+    .map_err(|e| AppError::backend("redis", e))?;
+    timings.insert("redis", start.elapsed().as_millis());
+
+    Ok((StatusCode::OK, Json(json!({ "dataset": dataset, "all": all, "timings": timings }))))
 }
\ No newline at end of file
@@ -0,0 +1,152 @@
+use crate::models::series::{Record, Schema, Value};
+use crate::models::rune_pool::{DbInterval, DbMeta};
+use std::error::Error;
+
+const RAW_INTERVAL_LEN: usize = 32;
+const RAW_META_LEN: usize = 48;
+
+/// Value serialization used by the key-value backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Json,
+    Bincode,
+    MessagePack,
+    Cbor,
+    /// Fixed-width little-endian `u64` fields: 32 bytes per interval, 48 for meta. Series
+    /// records are their two timestamps and then 8 bytes per schema field.
+    Raw,
+}
+
+fn write_raw(values: &[u64]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn read_raw<const N: usize>(bytes: &[u8]) -> Result<[u64; N], Box<dyn Error>> {
+    if bytes.len() != N * 8 {
+        return Err(format!("Expected {} raw bytes, got {}", N * 8, bytes.len()).into());
+    }
+    let mut values = [0u64; N];
+    for (value, chunk) in values.iter_mut().zip(bytes.chunks_exact(8)) {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(chunk);
+        *value = u64::from_le_bytes(buf);
+    }
+    Ok(values)
+}
+
+impl Codec {
+    pub fn parse(value: &str) -> Result<Self, Box<dyn Error>> {
+        match value {
+            "json" => Ok(Codec::Json),
+            "bincode" => Ok(Codec::Bincode),
+            "msgpack" => Ok(Codec::MessagePack),
+            "cbor" => Ok(Codec::Cbor),
+            "raw" => Ok(Codec::Raw),
+            other => Err(format!("Unknown codec: {}", other).into()),
+        }
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(match self {
+            Codec::Json => serde_json::to_vec(value)?,
+            Codec::Bincode => bincode::serialize(value)?,
+            Codec::MessagePack => rmp_serde::to_vec(value)?,
+            Codec::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(value, &mut buf)?;
+                buf
+            }
+            Codec::Raw => unreachable!("raw values are laid out by hand"),
+        })
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+        Ok(match self {
+            Codec::Json => serde_json::from_slice(bytes)?,
+            Codec::Bincode => bincode::deserialize(bytes)?,
+            Codec::MessagePack => rmp_serde::from_slice(bytes)?,
+            Codec::Cbor => ciborium::from_reader(bytes)?,
+            Codec::Raw => unreachable!("raw values are laid out by hand"),
+        })
+    }
+
+    pub fn encode_meta(&self, meta: &DbMeta) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            Codec::Raw => Ok(write_raw(&[
+                meta.start_time,
+                meta.end_time,
+                meta.start_count,
+                meta.end_count,
+                meta.start_units,
+                meta.end_units,
+            ])),
+            _ => self.encode(meta),
+        }
+    }
+
+    pub fn decode_meta(&self, bytes: &[u8]) -> Result<DbMeta, Box<dyn Error>> {
+        match self {
+            Codec::Raw => {
+                let [start_time, end_time, start_count, end_count, start_units, end_units] =
+                    read_raw::<{ RAW_META_LEN / 8 }>(bytes)?;
+                Ok(DbMeta { start_time, end_time, start_count, end_count, start_units, end_units })
+            }
+            _ => self.decode(bytes),
+        }
+    }
+
+    pub fn encode_interval(&self, interval: &DbInterval) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            Codec::Raw => Ok(write_raw(&[interval.start_time, interval.end_time, interval.count, interval.units])),
+            _ => self.encode(interval),
+        }
+    }
+
+    pub fn decode_interval(&self, bytes: &[u8]) -> Result<DbInterval, Box<dyn Error>> {
+        match self {
+            Codec::Raw => {
+                let [start_time, end_time, count, units] = read_raw::<{ RAW_INTERVAL_LEN / 8 }>(bytes)?;
+                Ok(DbInterval { start_time, end_time, count, units })
+            }
+            _ => self.decode(bytes),
+        }
+    }
+
+    pub fn encode_record(&self, record: &Record) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            Codec::Raw => {
+                let mut values = vec![record.start_time, record.end_time];
+                values.extend(record.values.iter().map(|value| value.to_bits()));
+                Ok(write_raw(&values))
+            }
+            _ => self.encode(record),
+        }
+    }
+
+    /// Raw records carry no type information, so the schema says how to read each field.
+    pub fn decode_record(&self, schema: &Schema, bytes: &[u8]) -> Result<Record, Box<dyn Error>> {
+        let record = match self {
+            Codec::Raw => {
+                let expected = (2 + schema.fields.len()) * 8;
+                if bytes.len() != expected {
+                    return Err(format!("Expected {} raw bytes, got {}", expected, bytes.len()).into());
+                }
+                let [start_time, end_time] = read_raw::<2>(&bytes[..16])?;
+                let values = schema
+                    .fields
+                    .iter()
+                    .zip(bytes[16..].chunks_exact(8))
+                    .map(|(field, chunk)| {
+                        let mut buf = [0u8; 8];
+                        buf.copy_from_slice(chunk);
+                        Value::from_bits(field.kind, u64::from_le_bytes(buf))
+                    })
+                    .collect();
+                Record { start_time, end_time, values }
+            }
+            _ => self.decode(bytes)?,
+        };
+        schema.check(&record)?;
+        Ok(record)
+    }
+}
@@ -0,0 +1,53 @@
+use std::error::Error;
+
+/// Dataset that data written before named datasets existed is migrated into.
+pub const LEGACY: &str = "default";
+
+/// Names end up in key prefixes, file names and SQL values, so they are kept to a safe
+/// alphabet. Without `/`, no dataset's key prefix is a prefix of another's.
+pub fn check_name(name: &str) -> Result<(), Box<dyn Error>> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    if !valid {
+        return Err(format!("Invalid dataset name '{}': use 1-64 letters, digits, '_' or '-'", name).into());
+    }
+    Ok(())
+}
+
+/// Key of a dataset's meta in the ordered key-value stores that keep everything in one
+/// keyspace; the others key their meta table by the bare name.
+pub fn meta_key(dataset: &str) -> Vec<u8> {
+    format!("meta/{}", dataset).into_bytes()
+}
+
+/// Key layout for a dataset's intervals in the ordered key-value stores: `interval/{dataset}/`
+/// followed by the big-endian start time, so each dataset is a contiguous, time-ordered key
+/// range that can't overlap the `history_` keys.
+pub fn prefix(dataset: &str) -> Vec<u8> {
+    format!("interval/{}/", dataset).into_bytes()
+}
+
+/// Prefix of every dataset's intervals.
+pub const INTERVALS_PREFIX: &[u8] = b"interval/";
+
+pub fn interval_key(dataset: &str, start_time: u64) -> Vec<u8> {
+    let mut key = prefix(dataset);
+    key.extend_from_slice(&start_time.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unsafe_names() {
+        for name in ["hourly", "day-1", "midgard_v2"] {
+            assert!(check_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", "a/b", "../x", "it's", &"x".repeat(65)] {
+            assert!(check_name(name).is_err(), "{}", name);
+        }
+    }
+}
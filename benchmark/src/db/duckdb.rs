@@ -0,0 +1,385 @@
+use crate::config::Config;
+use crate::error::NotFound;
+use crate::db::dataset;
+use crate::db::history;
+use crate::models::series::{self, FieldKind, Record, Schema, Value};
+use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
+use duckdb::{params, Connection};
+use std::error::Error;
+use std::path::Path;
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        dataset VARCHAR PRIMARY KEY,
+        start_time UBIGINT NOT NULL,
+        end_time UBIGINT NOT NULL,
+        start_count UBIGINT NOT NULL,
+        end_count UBIGINT NOT NULL,
+        start_units UBIGINT NOT NULL,
+        end_units UBIGINT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS intervals (
+        dataset VARCHAR NOT NULL,
+        start_time UBIGINT NOT NULL,
+        end_time UBIGINT NOT NULL,
+        count UBIGINT NOT NULL,
+        units UBIGINT NOT NULL,
+        PRIMARY KEY (dataset, start_time)
+    );
+";
+
+/// Tables written before datasets were introduced have no `dataset` column. Their rows are
+/// moved into the `default` dataset on open.
+const LEGACY_TABLES: &str = "
+    SELECT table_name FROM information_schema.tables t
+    WHERE (table_name IN ('meta', 'intervals') OR starts_with(table_name, 'history_'))
+      AND NOT EXISTS (
+        SELECT 1 FROM information_schema.columns c
+        WHERE c.table_name = t.table_name AND c.column_name = 'dataset'
+      )
+";
+
+/// Embedded columnar store. A DuckDB `Connection` is not `Sync`, hence the mutex.
+pub struct DuckDbClient {
+    conn: Mutex<Connection>,
+    in_memory: bool,
+}
+
+fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl DuckDbClient {
+    pub fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let in_memory = config.duckdb_path == ":memory:";
+        let mut conn = if in_memory {
+            Connection::open_in_memory()?
+        } else {
+            if let Some(parent) = Path::new(&config.duckdb_path).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            Connection::open(&config.duckdb_path)?
+        };
+        conn.execute_batch(&format!("DROP TABLE IF EXISTS {}", series::RETIRED_TABLE))?;
+        Self::create_tables(&mut conn)?;
+        for schema in series::ALL {
+            let columns: Vec<String> = conn
+                .prepare("SELECT column_name FROM information_schema.columns WHERE table_name = ?")?
+                .query_map([schema.table], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            history::check_columns(schema, &columns)?;
+        }
+        Ok(DuckDbClient { conn: Mutex::new(conn), in_memory })
+    }
+
+    /// Creates the tables, moving the rows of any legacy table into the `default` dataset of
+    /// its replacement in the same transaction.
+    fn create_tables(conn: &mut Connection) -> Result<(), Box<dyn Error>> {
+        let tx = conn.transaction()?;
+        let legacy: Vec<String> = tx
+            .prepare(LEGACY_TABLES)?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        for table in &legacy {
+            tx.execute_batch(&format!("ALTER TABLE {} RENAME TO {}_legacy", table, table))?;
+        }
+        tx.execute_batch(SCHEMA)?;
+        for schema in series::ALL {
+            tx.execute_batch(&history::create_table(schema, "UBIGINT", "DOUBLE"))?;
+        }
+        for table in &legacy {
+            let columns: Vec<String> = tx
+                .prepare("SELECT column_name FROM information_schema.columns WHERE table_name = ? ORDER BY ordinal_position")?
+                .query_map([format!("{}_legacy", table)], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            let columns = columns.join(", ");
+            tx.execute_batch(&format!(
+                "INSERT INTO {table} (dataset, {columns}) SELECT {dataset}, {columns} FROM {table}_legacy;
+                 DROP TABLE {table}_legacy;",
+                dataset = sql_string(dataset::LEGACY),
+            ))?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn update_rune_pool(&self, dataset: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM meta WHERE dataset = ?", params![dataset])?;
+        tx.execute("DELETE FROM intervals WHERE dataset = ?", params![dataset])?;
+
+        let meta = &response.meta;
+        tx.execute(
+            "INSERT INTO meta VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![dataset, meta.start_time, meta.end_time, meta.start_count, meta.end_count, meta.start_units, meta.end_units],
+        )?;
+
+        // The appender is DuckDB's bulk-load path; row-by-row INSERTs are far slower.
+        {
+            let mut appender = tx.appender("intervals")?;
+            for interval in &response.intervals {
+                appender.append_row(params![dataset, interval.start_time, interval.end_time, interval.count, interval.units])?;
+            }
+            appender.flush()?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_rune_pool(&self, dataset: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let meta = Self::read_meta(&conn, dataset)?;
+        let intervals = Self::query_intervals(
+            &conn,
+            "SELECT start_time, end_time, count, units FROM intervals WHERE dataset = ? ORDER BY start_time",
+            params![dataset],
+        )?;
+        Ok(DbRunePoolResponse { meta, intervals })
+    }
+
+    /// Aggregates intervals into `seconds`-wide buckets inside DuckDB. `count` and
+    /// `units` are gauges, so each bucket keeps the value of its last interval.
+    pub fn resample(&self, dataset: &str, seconds: u64) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let meta = Self::read_meta(&conn, dataset)?;
+        let intervals = Self::query_intervals(
+            &conn,
+            "SELECT min(start_time), max(end_time), arg_max(count, start_time), arg_max(units, start_time)
+             FROM intervals
+             WHERE dataset = ?
+             GROUP BY start_time // ?
+             ORDER BY 1",
+            params![dataset, seconds],
+        )?;
+        Ok(DbRunePoolResponse { meta, intervals })
+    }
+
+    fn read_meta(conn: &Connection, dataset: &str) -> Result<DbMeta, Box<dyn Error>> {
+        let mut stmt = conn.prepare(
+            "SELECT start_time, end_time, start_count, end_count, start_units, end_units FROM meta WHERE dataset = ?",
+        )?;
+        let mut rows = stmt.query(params![dataset])?;
+        let row = rows.next()?.ok_or(NotFound)?;
+        Ok(DbMeta {
+            start_time: row.get(0)?,
+            end_time: row.get(1)?,
+            start_count: row.get(2)?,
+            end_count: row.get(3)?,
+            start_units: row.get(4)?,
+            end_units: row.get(5)?,
+        })
+    }
+
+    fn query_intervals(conn: &Connection, sql: &str, params: &[&dyn duckdb::ToSql]) -> Result<Vec<DbInterval>, Box<dyn Error>> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| {
+            Ok(DbInterval {
+                start_time: row.get(0)?,
+                end_time: row.get(1)?,
+                count: row.get(2)?,
+                units: row.get(3)?,
+            })
+        })?;
+        let mut intervals = Vec::new();
+        for interval in rows {
+            intervals.push(interval?);
+        }
+        Ok(intervals)
+    }
+
+    pub fn update_series(&self, schema: &Schema, dataset: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            &format!("DELETE FROM {} WHERE dataset = ? AND scope = ?", schema.table),
+            params![dataset, scope],
+        )?;
+        {
+            let mut appender = tx.appender(schema.table)?;
+            for record in records {
+                let columns = [
+                    duckdb::types::Value::Text(dataset.to_string()),
+                    duckdb::types::Value::Text(scope.to_string()),
+                    duckdb::types::Value::UBigInt(record.start_time),
+                    duckdb::types::Value::UBigInt(record.end_time),
+                ]
+                .into_iter()
+                .chain(record.values.iter().map(|value| match *value {
+                    Value::U64(n) => duckdb::types::Value::UBigInt(n),
+                    Value::F64(x) => duckdb::types::Value::Double(x),
+                }));
+                appender.append_row(duckdb::appender_params_from_iter(columns))?;
+            }
+            appender.flush()?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM {} WHERE dataset = ? AND scope = ? ORDER BY start_time",
+            history::columns(schema),
+            schema.table
+        ))?;
+        let mapped = stmt.query_map(params![dataset, scope], |row| {
+            let mut values = Vec::with_capacity(schema.fields.len());
+            for (i, field) in schema.fields.iter().enumerate() {
+                values.push(match field.kind {
+                    FieldKind::U64 => Value::U64(row.get(i + 2)?),
+                    FieldKind::F64 => Value::F64(row.get(i + 2)?),
+                });
+            }
+            Ok(Record { start_time: row.get(0)?, end_time: row.get(1)?, values })
+        })?;
+        let mut records = Vec::new();
+        for record in mapped {
+            records.push(record?);
+        }
+        if records.is_empty() {
+            return Err(NotFound.into());
+        }
+        Ok(records)
+    }
+
+    /// Writes `dataset`'s `meta.parquet` and `intervals.parquet` into `dir`. The files leave
+    /// out the dataset column, so they can be imported under any name.
+    pub fn export_parquet(&self, dataset: &str, dir: &str) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(dir)?;
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        for table in ["meta", "intervals"] {
+            let file = Path::new(dir).join(format!("{}.parquet", table));
+            conn.execute_batch(&format!(
+                "COPY (SELECT * EXCLUDE (dataset) FROM {} WHERE dataset = {} ORDER BY start_time) TO {} (FORMAT PARQUET)",
+                table,
+                sql_string(dataset),
+                sql_string(&file.to_string_lossy())
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Replaces `dataset` with the files written by `export_parquet`.
+    pub fn import_parquet(&self, dataset: &str, dir: &str) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM meta WHERE dataset = ?", params![dataset])?;
+        tx.execute("DELETE FROM intervals WHERE dataset = ?", params![dataset])?;
+        for table in ["meta", "intervals"] {
+            let file = Path::new(dir).join(format!("{}.parquet", table));
+            tx.execute_batch(&format!(
+                "INSERT INTO {} SELECT {}, * FROM read_parquet({})",
+                table,
+                sql_string(dataset),
+                sql_string(&file.to_string_lossy())
+            ))?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Checkpoints so the WAL is folded into blocks, then counts used blocks. In-memory
+    /// databases have no blocks, so their buffer memory is reported instead.
+    pub fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let query = if self.in_memory {
+            "SELECT sum(memory_usage_bytes)::BIGINT FROM duckdb_memory()"
+        } else {
+            conn.execute_batch("CHECKPOINT")?;
+            "SELECT used_blocks * block_size FROM pragma_database_size() WHERE database_name = current_database()"
+        };
+        let size: i64 = conn.query_row(query, [], |row| row.get(0))?;
+        Ok(size as u64)
+    }
+
+    /// Interval and history rows across every dataset.
+    pub fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let rows: i64 = conn.query_row(&history::count_rows(), [], |row| row.get(0))?;
+        Ok(rows as u64)
+    }
+
+    pub fn clear_dataset(&self, dataset: &str) -> Result<(), Box<dyn Error>> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tables = ["meta", "intervals"].into_iter().chain(series::ALL.iter().map(|schema| schema.table));
+        for table in tables {
+            conn.execute(&format!("DELETE FROM {} WHERE dataset = ?", table), params![dataset])?;
+        }
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute_batch("DELETE FROM meta; DELETE FROM intervals;")?;
+        for schema in series::ALL {
+            conn.execute_batch(&format!("DELETE FROM {};", schema.table))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures::{self, dataset, HOURLY};
+    use crate::models::series::TVL;
+    use tempfile::TempDir;
+
+    #[test]
+    fn resamples_and_round_trips_parquet() {
+        let dir = TempDir::new().unwrap();
+        let duckdb = DuckDbClient::new(&fixtures::config(&dir)).unwrap();
+        let expected = dataset(72, 0);
+        duckdb.update_rune_pool(HOURLY, &expected).unwrap();
+        assert_eq!(duckdb.get_rune_pool(HOURLY).unwrap(), expected);
+        assert!(duckdb.storage_size().unwrap() > 0);
+        assert_eq!(duckdb.resample(HOURLY, 86_400).unwrap(), expected.resample(86_400));
+
+        let parquet_dir = dir.path().join("parquet").to_string_lossy().into_owned();
+        duckdb.export_parquet(HOURLY, &parquet_dir).unwrap();
+        duckdb.clear().unwrap();
+        duckdb.import_parquet(HOURLY, &parquet_dir).unwrap();
+        assert_eq!(duckdb.get_rune_pool(HOURLY).unwrap(), expected);
+    }
+
+    #[test]
+    fn legacy_tables_become_the_default_dataset() {
+        let dir = TempDir::new().unwrap();
+        let mut config = fixtures::config(&dir);
+        config.duckdb_path = dir.path().join("runepool.duckdb").to_string_lossy().into_owned();
+        let (data, records) = (dataset(6, 0), fixtures::history(&TVL, 3, 0));
+        {
+            // Write through the current layout, then strip the dataset column from each table.
+            let duckdb = DuckDbClient::new(&config).unwrap();
+            duckdb.update_rune_pool(HOURLY, &data).unwrap();
+            duckdb.update_series(&TVL, HOURLY, "", &records).unwrap();
+            let conn = duckdb.conn.lock().unwrap();
+            for table in ["meta", "intervals", TVL.table] {
+                conn.execute_batch(&format!(
+                    "CREATE TABLE old AS SELECT * EXCLUDE (dataset) FROM {table};
+                     DROP TABLE {table};
+                     ALTER TABLE old RENAME TO {table};"
+                ))
+                .unwrap();
+            }
+        }
+
+        let duckdb = DuckDbClient::new(&config).unwrap();
+        let legacy = crate::db::dataset::LEGACY;
+        assert_eq!(duckdb.get_rune_pool(legacy).unwrap(), data);
+        assert_eq!(duckdb.get_series(&TVL, legacy, "").unwrap(), records);
+    }
+
+    #[test]
+    fn unsigned_extremes_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let duckdb = DuckDbClient::new(&fixtures::config(&dir)).unwrap();
+        let data = fixtures::extreme(u64::MAX);
+        duckdb.update_rune_pool(HOURLY, &data).unwrap();
+        assert_eq!(duckdb.get_rune_pool(HOURLY).unwrap(), data);
+    }
+}
@@ -0,0 +1,112 @@
+//! Data and configuration shared by the backends' tests.
+use crate::config::Config;
+use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
+use crate::models::series::{FieldKind, Record, Schema, Value, DEPTHS, SWAPS, TVL};
+use tempfile::TempDir;
+
+pub const HOURLY: &str = "hourly";
+pub const DAILY: &str = "daily";
+
+/// Every name `KV_CODEC` accepts.
+pub const CODECS: [&str; 5] = ["json", "bincode", "msgpack", "cbor", "raw"];
+
+/// File-backed stores rooted in `dir`; SQLite, DuckDB and SurrealDB in memory.
+pub fn config(dir: &TempDir) -> Config {
+    let path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+    let mut config = Config::load();
+    config.leveldb_path = path("leveldb");
+    config.rocksdb_path = path("rocksdb");
+    config.sled_path = path("sled");
+    config.redb_path = path("runepool.redb");
+    config.lmdb_path = path("lmdb");
+    config.flatfile_path = path("flatfile");
+    config.flatfile_index_every = 4;
+    config.sqlite_path = ":memory:".to_string();
+    config.duckdb_path = ":memory:".to_string();
+    config.surrealdb_engine = "mem".to_string();
+    config
+}
+
+/// `len` hourly intervals starting `offset` seconds after a fixed time, with a matching meta.
+pub fn dataset(len: u64, offset: u64) -> DbRunePoolResponse {
+    let start = 1_700_000_000 + offset;
+    let intervals: Vec<DbInterval> = (0..len)
+        .map(|i| DbInterval {
+            start_time: start + i * 3600,
+            end_time: start + (i + 1) * 3600,
+            count: 100 + i,
+            units: 1_000_000_000 + i * 7,
+        })
+        .collect();
+    DbRunePoolResponse {
+        meta: DbMeta {
+            start_time: start,
+            end_time: start + len * 3600,
+            start_count: 100,
+            end_count: 100 + len - 1,
+            start_units: 1_000_000_000,
+            end_units: 1_000_000_000 + (len - 1) * 7,
+        },
+        intervals,
+    }
+}
+
+/// Two datasets with overlapping start times, then a rewrite of the first that must leave
+/// the second alone.
+pub fn dataset_writes() -> [(&'static str, DbRunePoolResponse); 3] {
+    [(HOURLY, dataset(24, 0)), (DAILY, dataset(7, 0)), (HOURLY, dataset(12, 3600))]
+}
+
+/// `len` daily records of `schema`, with distinct values per field and record.
+pub fn history(schema: &Schema, len: u64, offset: u64) -> Vec<Record> {
+    (0..len)
+        .map(|i| Record {
+            start_time: 1_700_000_000 + offset + i * 86_400,
+            end_time: 1_700_000_000 + offset + (i + 1) * 86_400,
+            values: schema
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(j, field)| match field.kind {
+                    FieldKind::U64 => Value::U64(1_000_000_000 * j as u64 + i),
+                    FieldKind::F64 => Value::F64(1.5 + i as f64 / 8.0),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Series a store must keep apart. "BTC" is a prefix of "BTC.BTC", so a range scan for one
+/// must not pick up the other's records.
+pub fn series() -> [(&'static Schema, &'static str, Vec<Record>); 3] {
+    [
+        (&DEPTHS, "BTC.BTC", history(&DEPTHS, 6, 0)),
+        (&DEPTHS, "BTC", history(&DEPTHS, 3, 3600)),
+        (&TVL, "", history(&TVL, 4, 0)),
+    ]
+}
+
+/// Written under each series before its real records, which must replace it.
+pub fn stale(schema: &Schema) -> Vec<Record> {
+    history(schema, 9, 60)
+}
+
+/// `units` at the top of a store's integer range: `i64::MAX` where integers are signed,
+/// `u64::MAX` where they are not.
+pub fn extreme(units: u64) -> DbRunePoolResponse {
+    let mut data = dataset(2, 0);
+    data.meta.end_units = units;
+    data.intervals[1].units = units;
+    data
+}
+
+/// Two swaps records whose first field is `value`.
+pub fn extreme_records(value: u64) -> Vec<Record> {
+    history(&SWAPS, 2, 0)
+        .into_iter()
+        .map(|mut record| {
+            record.values[0] = Value::U64(value);
+            record
+        })
+        .collect()
+}
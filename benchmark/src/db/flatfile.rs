@@ -0,0 +1,634 @@
+use crate::config::Config;
+use crate::error::NotFound;
+use crate::db::dataset;
+use crate::db::storage::dir_size;
+use crate::models::series::{self, Record, Schema, Value};
+use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Segment of generation 0, the only one before updates were versioned.
+const SEGMENT_FILE: &str = "intervals.log";
+const META_FILE: &str = "meta.bin";
+const DATASETS_DIR: &str = "datasets";
+const HISTORY_DIR: &str = "history";
+const INTERVAL_LEN: usize = 32;
+/// The six meta fields, then the generation of the segment they belong to.
+const META_LEN: usize = 56;
+/// Meta written before updates were versioned, which always belongs to `SEGMENT_FILE`.
+const UNVERSIONED_META_LEN: usize = 48;
+const RECORD_LEN: u64 = 4 + INTERVAL_LEN as u64;
+
+/// When appended records are forced to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// Leave flushing to the OS.
+    Never,
+    /// One `fsync` after each update.
+    Batch,
+    /// `fsync` after every appended record.
+    Always,
+}
+
+impl FsyncPolicy {
+    pub fn parse(value: &str) -> Result<Self, Box<dyn Error>> {
+        match value {
+            "never" => Ok(FsyncPolicy::Never),
+            "batch" => Ok(FsyncPolicy::Batch),
+            "always" => Ok(FsyncPolicy::Always),
+            other => Err(format!("Unknown flat file fsync policy: {}", other).into()),
+        }
+    }
+}
+
+struct Segment {
+    file: File,
+    generation: u64,
+    /// `(start_time, offset)` of every `index_every`-th record.
+    index: Vec<(u64, u64)>,
+    records: u64,
+}
+
+/// Length-prefixed `DbInterval` records in one segment file per dataset, sorted by
+/// `start_time`, with a sparse in-memory timestamp index rebuilt when the segment is first
+/// opened. An update replaces the whole dataset: it writes the next generation's segment,
+/// then renames a meta naming that generation over the old one. The rename is the commit,
+/// so an update cut short leaves the previous dataset in place.
+pub struct FlatFileClient {
+    dir: PathBuf,
+    fsync: FsyncPolicy,
+    index_every: u64,
+    /// Open segments by dataset name.
+    segments: Mutex<HashMap<String, Segment>>,
+}
+
+fn encode_interval(interval: &DbInterval) -> [u8; INTERVAL_LEN] {
+    let mut buf = [0u8; INTERVAL_LEN];
+    buf[0..8].copy_from_slice(&interval.start_time.to_le_bytes());
+    buf[8..16].copy_from_slice(&interval.end_time.to_le_bytes());
+    buf[16..24].copy_from_slice(&interval.count.to_le_bytes());
+    buf[24..32].copy_from_slice(&interval.units.to_le_bytes());
+    buf
+}
+
+fn read_u64(buf: &[u8], at: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[at..at + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn decode_interval(buf: &[u8]) -> DbInterval {
+    DbInterval {
+        start_time: read_u64(buf, 0),
+        end_time: read_u64(buf, 8),
+        count: read_u64(buf, 16),
+        units: read_u64(buf, 24),
+    }
+}
+
+/// `intervals.log` for generation 0, `intervals.{generation}.log` after that.
+fn segment_file(generation: u64) -> String {
+    if generation == 0 {
+        SEGMENT_FILE.to_string()
+    } else {
+        format!("intervals.{}.log", generation)
+    }
+}
+
+/// The meta in `dir`, and the generation of the segment it was committed with.
+fn read_meta_file(dir: &Path) -> Result<(DbMeta, u64), Box<dyn Error>> {
+    let buf = match fs::read(dir.join(META_FILE)) {
+        Ok(buf) => buf,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Err(NotFound.into()),
+        Err(e) => return Err(e.into()),
+    };
+    let generation = match buf.len() {
+        META_LEN => read_u64(&buf, 48),
+        UNVERSIONED_META_LEN => 0,
+        _ => return Err("Corrupt flat file meta".into()),
+    };
+    let meta = DbMeta {
+        start_time: read_u64(&buf, 0),
+        end_time: read_u64(&buf, 8),
+        start_count: read_u64(&buf, 16),
+        end_count: read_u64(&buf, 24),
+        start_units: read_u64(&buf, 32),
+        end_units: read_u64(&buf, 40),
+    };
+    Ok((meta, generation))
+}
+
+/// Makes the renames in `dir` durable.
+fn sync_dir(dir: &Path) -> Result<(), Box<dyn Error>> {
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/// Reads the next record, or `None` at a clean end of file.
+fn read_record(reader: &mut impl Read) -> Result<Option<DbInterval>, Box<dyn Error>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len != INTERVAL_LEN {
+        return Err(format!("Corrupt flat file record of length {}", len).into());
+    }
+    let mut buf = [0u8; INTERVAL_LEN];
+    reader.read_exact(&mut buf)?;
+    Ok(Some(decode_interval(&buf)))
+}
+
+/// `start_time`, `end_time` and 8 bytes per field, behind a `u32` length prefix.
+fn write_series_record(writer: &mut impl Write, record: &Record) -> Result<(), Box<dyn Error>> {
+    let len = 16 + 8 * record.values.len();
+    writer.write_all(&u32::try_from(len)?.to_le_bytes())?;
+    writer.write_all(&record.start_time.to_le_bytes())?;
+    writer.write_all(&record.end_time.to_le_bytes())?;
+    for value in &record.values {
+        writer.write_all(&value.to_bits().to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_series_record(reader: &mut impl Read, schema: &Schema) -> Result<Option<Record>, Box<dyn Error>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len != 16 + 8 * schema.fields.len() {
+        return Err(format!("Corrupt flat file {} record of length {}", schema.name, len).into());
+    }
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    let values = schema
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| Value::from_bits(field.kind, read_u64(&buf, 16 + i * 8)))
+        .collect();
+    Ok(Some(Record { start_time: read_u64(&buf, 0), end_time: read_u64(&buf, 8), values }))
+}
+
+/// Directories directly under `dir`, or none if it doesn't exist yet.
+fn subdirs(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+/// Moves the segment and meta of the single-dataset layout, kept directly under `dir`,
+/// into the `default` dataset. They are dropped if that dataset already exists.
+fn migrate_legacy_files(dir: &Path) -> Result<(), Box<dyn Error>> {
+    let target = dir.join(DATASETS_DIR).join(dataset::LEGACY);
+    let keep = !target.exists();
+    for file in [SEGMENT_FILE, META_FILE] {
+        let path = dir.join(file);
+        if !path.exists() {
+            continue;
+        }
+        if keep {
+            fs::create_dir_all(&target)?;
+            fs::rename(&path, target.join(file))?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+impl FlatFileClient {
+    pub fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let dir = PathBuf::from(&config.flatfile_path);
+        fs::create_dir_all(&dir)?;
+        migrate_legacy_files(&dir)?;
+        Ok(FlatFileClient {
+            dir,
+            fsync: FsyncPolicy::parse(&config.flatfile_fsync)?,
+            index_every: config.flatfile_index_every.max(1),
+            segments: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// `datasets/{name}`, holding the dataset's segment and meta.
+    fn dataset_dir(&self, name: &str) -> PathBuf {
+        self.dir.join(DATASETS_DIR).join(name)
+    }
+
+    /// Runs `f` on the dataset's meta and the segment committed with it, opening and indexing
+    /// the segment on first use. Both are read under the segments lock, so an update can't
+    /// land between them.
+    fn with_segment<T>(
+        &self,
+        name: &str,
+        f: impl FnOnce(DbMeta, &mut Segment) -> Result<T, Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        let mut segments = self.segments.lock().map_err(|e| e.to_string())?;
+        let dir = self.dataset_dir(name);
+        let (meta, generation) = read_meta_file(&dir)?;
+        if !matches!(segments.get(name), Some(segment) if segment.generation == generation) {
+            let file = OpenOptions::new().read(true).write(true).open(dir.join(segment_file(generation)))?;
+            let mut segment = Segment { file, generation, index: Vec::new(), records: 0 };
+            Self::rebuild_index(&mut segment, self.index_every)?;
+            segments.insert(name.to_string(), segment);
+        }
+        let segment = segments.get_mut(name).ok_or("Flat file segment missing after opening it")?;
+        f(meta, segment)
+    }
+
+    /// Indexes every complete record, truncating a partial one left at the end by an
+    /// interrupted write. Fails if the records are out of order, since seeks rely on it.
+    fn rebuild_index(segment: &mut Segment, index_every: u64) -> Result<(), Box<dyn Error>> {
+        segment.index.clear();
+        segment.records = 0;
+        let len = segment.file.metadata()?.len();
+        let complete = len / RECORD_LEN;
+        if len != complete * RECORD_LEN {
+            segment.file.set_len(complete * RECORD_LEN)?;
+        }
+        segment.file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(&segment.file);
+        let mut previous = None;
+        for i in 0..complete {
+            let interval = read_record(&mut reader)?.ok_or("Flat file segment ended early")?;
+            if previous.is_some_and(|start_time| interval.start_time < start_time) {
+                return Err(format!("Flat file record {} is out of start_time order", i).into());
+            }
+            previous = Some(interval.start_time);
+            if i.is_multiple_of(index_every) {
+                segment.index.push((interval.start_time, i * RECORD_LEN));
+            }
+        }
+        segment.records = complete;
+        Ok(())
+    }
+
+    /// Writes the next generation's segment, then commits it by renaming its meta into
+    /// place, and only then removes the previous segment.
+    pub fn update_rune_pool(&self, name: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let mut segments = self.segments.lock().map_err(|e| e.to_string())?;
+        let dir = self.dataset_dir(name);
+        fs::create_dir_all(&dir)?;
+        let previous = match read_meta_file(&dir) {
+            Ok((_, generation)) => Some(generation),
+            Err(e) if e.is::<NotFound>() => None,
+            Err(e) => return Err(e),
+        };
+        let generation = previous.map_or(1, |generation| generation + 1);
+
+        let segment = self.write_segment(&dir, generation, &response.intervals)?;
+        self.write_meta(&dir, &response.meta, generation)?;
+        segments.insert(name.to_string(), segment);
+        if let Some(previous) = previous {
+            if let Err(e) = fs::remove_file(dir.join(segment_file(previous))) {
+                if e.kind() != ErrorKind::NotFound {
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `intervals` sorted by `start_time`, the order the index and range scans depend
+    /// on, to a fresh segment file. A file left by an update that never committed is overwritten.
+    fn write_segment(&self, dir: &Path, generation: u64, intervals: &[DbInterval]) -> Result<Segment, Box<dyn Error>> {
+        let mut intervals = intervals.to_vec();
+        intervals.sort_by_key(|interval| interval.start_time);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(dir.join(segment_file(generation)))?;
+        let mut segment = Segment { file, generation, index: Vec::new(), records: 0 };
+
+        {
+            let mut writer = BufWriter::new(&segment.file);
+            for (i, interval) in intervals.iter().enumerate() {
+                writer.write_all(&(INTERVAL_LEN as u32).to_le_bytes())?;
+                writer.write_all(&encode_interval(interval))?;
+                if self.fsync == FsyncPolicy::Always {
+                    writer.flush()?;
+                    writer.get_ref().sync_data()?;
+                }
+                let i = i as u64;
+                if i.is_multiple_of(self.index_every) {
+                    segment.index.push((interval.start_time, i * RECORD_LEN));
+                }
+            }
+            writer.flush()?;
+        }
+        segment.records = intervals.len() as u64;
+        if self.fsync == FsyncPolicy::Batch {
+            segment.file.sync_data()?;
+        }
+        Ok(segment)
+    }
+
+    /// Meta is rewritten whole and swapped in with a rename, which commits `generation`.
+    fn write_meta(&self, dir: &Path, meta: &DbMeta, generation: u64) -> Result<(), Box<dyn Error>> {
+        let mut buf = [0u8; META_LEN];
+        let values = [meta.start_time, meta.end_time, meta.start_count, meta.end_count, meta.start_units, meta.end_units, generation];
+        for (i, value) in values.iter().enumerate() {
+            buf[i * 8..i * 8 + 8].copy_from_slice(&value.to_le_bytes());
+        }
+        let tmp = dir.join(format!("{}.tmp", META_FILE));
+        let mut file = File::create(&tmp)?;
+        file.write_all(&buf)?;
+        if self.fsync != FsyncPolicy::Never {
+            file.sync_data()?;
+        }
+        fs::rename(tmp, dir.join(META_FILE))?;
+        if self.fsync != FsyncPolicy::Never {
+            sync_dir(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Full scan of the segment.
+    pub fn get_rune_pool(&self, name: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        self.scan_from(name, |_| 0, |_| true, |_| false)
+    }
+
+    /// Intervals with `from <= start_time < to`, seeking via the sparse index.
+    pub fn get_range(&self, name: &str, from: u64, to: u64) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let offset = |segment: &Segment| {
+            let block = segment.index.partition_point(|(start_time, _)| *start_time <= from);
+            block.checked_sub(1).map(|i| segment.index[i].1).unwrap_or(0)
+        };
+        self.scan_from(name, offset, |i| i.start_time >= from, |i| i.start_time >= to)
+    }
+
+    /// The last record, read directly at its computed offset. `None` when the dataset is
+    /// stored without intervals; `NotFound` when it isn't stored at all.
+    pub fn get_latest(&self, name: &str) -> Result<Option<DbInterval>, Box<dyn Error>> {
+        self.with_segment(name, |_, segment| {
+            if segment.records == 0 {
+                return Ok(None);
+            }
+            let offset = (segment.records - 1) * RECORD_LEN;
+            segment.file.seek(SeekFrom::Start(offset))?;
+            read_record(&mut segment.file)
+        })
+    }
+
+    /// Reads the meta, then records from the offset `offset` picks in the segment's index.
+    fn scan_from(
+        &self,
+        name: &str,
+        offset: impl FnOnce(&Segment) -> u64,
+        keep: impl Fn(&DbInterval) -> bool,
+        stop: impl Fn(&DbInterval) -> bool,
+    ) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        self.with_segment(name, |meta, segment| {
+            segment.file.seek(SeekFrom::Start(offset(segment)))?;
+            let mut reader = BufReader::new(&segment.file);
+            let mut intervals = Vec::new();
+            while let Some(interval) = read_record(&mut reader)? {
+                if stop(&interval) {
+                    break;
+                }
+                if keep(&interval) {
+                    intervals.push(interval);
+                }
+            }
+            Ok(DbRunePoolResponse { meta, intervals })
+        })
+    }
+
+    /// `history/{dataset}/{table}.log`, or `history/{dataset}/{table}-{scope}.log` for per-pool
+    /// series. Pools are used verbatim, so they must pass `series::check_pool`; tables hold no
+    /// `-`, so the name can't be mistaken for another table's.
+    fn history_file(&self, table: &str, dataset: &str, scope: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let name = if scope.is_empty() {
+            table.to_string()
+        } else {
+            series::check_pool(scope)?;
+            format!("{}-{}", table, scope)
+        };
+        Ok(self.dir.join(HISTORY_DIR).join(dataset).join(format!("{}.log", name)))
+    }
+
+    /// Series are small and always replaced whole, so each is rewritten and swapped in with a rename.
+    pub fn update_series(&self, schema: &Schema, dataset: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+        let path = self.history_file(schema.table, dataset, scope)?;
+        fs::create_dir_all(self.dir.join(HISTORY_DIR).join(dataset))?;
+        let tmp = path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp)?);
+            for record in records {
+                write_series_record(&mut writer, record)?;
+            }
+            writer.flush()?;
+            if self.fsync != FsyncPolicy::Never {
+                writer.get_ref().sync_data()?;
+            }
+        }
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    pub fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let file = match File::open(self.history_file(schema.table, dataset, scope)?) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(NotFound.into()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut reader = BufReader::new(file);
+        let mut records = Vec::new();
+        while let Some(record) = read_series_record(&mut reader, schema)? {
+            records.push(record);
+        }
+        if records.is_empty() {
+            return Err(NotFound.into());
+        }
+        Ok(records)
+    }
+
+    pub fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        dir_size(&self.dir)
+    }
+
+    /// Whole records in every committed segment and series file, counted from the file lengths.
+    pub fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let mut rows = 0;
+        for dir in subdirs(&self.dir.join(DATASETS_DIR))? {
+            let generation = match read_meta_file(&dir) {
+                Ok((_, generation)) => generation,
+                Err(e) if e.is::<NotFound>() => continue,
+                Err(e) => return Err(e),
+            };
+            rows += fs::metadata(dir.join(segment_file(generation)))?.len() / RECORD_LEN;
+        }
+        for dir in subdirs(&self.dir.join(HISTORY_DIR))? {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let Some(stem) = name.strip_suffix(".log") else { continue };
+                let table = stem.split('-').next().unwrap_or(stem);
+                if let Some(schema) = series::ALL.iter().find(|schema| schema.table == table) {
+                    rows += entry.metadata()?.len() / (20 + 8 * schema.fields.len() as u64);
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    pub fn clear_dataset(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        let mut segments = self.segments.lock().map_err(|e| e.to_string())?;
+        segments.remove(name);
+        for dir in [self.dataset_dir(name), self.dir.join(HISTORY_DIR).join(name)] {
+            if let Err(e) = fs::remove_dir_all(dir) {
+                if e.kind() != ErrorKind::NotFound {
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
+        let mut segments = self.segments.lock().map_err(|e| e.to_string())?;
+        segments.clear();
+        for dir in [DATASETS_DIR, HISTORY_DIR] {
+            if let Err(e) = fs::remove_dir_all(self.dir.join(dir)) {
+                if e.kind() != ErrorKind::NotFound {
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures::{self, dataset, HOURLY};
+    use tempfile::TempDir;
+
+    #[test]
+    fn reopened_store_serves_ranges() {
+        let dir = TempDir::new().unwrap();
+        let config = fixtures::config(&dir);
+        let expected = dataset(30, 0);
+        {
+            let flatfile = FlatFileClient::new(&config).unwrap();
+            flatfile.update_rune_pool(HOURLY, &dataset(50, 7200)).unwrap();
+            flatfile.update_rune_pool(HOURLY, &expected).unwrap();
+        }
+
+        // Reopen so the sparse index is rebuilt from the segment file.
+        let flatfile = FlatFileClient::new(&config).unwrap();
+        assert_eq!(flatfile.get_rune_pool(HOURLY).unwrap(), expected);
+        assert_eq!(flatfile.get_latest(HOURLY).unwrap().as_ref(), expected.intervals.last());
+        let ranges = [
+            (0, u64::MAX),
+            (expected.intervals[5].start_time, expected.intervals[17].start_time),
+            (expected.intervals[9].start_time + 1, expected.intervals[10].end_time),
+        ];
+        for (from, to) in ranges {
+            assert_eq!(flatfile.get_range(HOURLY, from, to).unwrap(), expected.range(from, to));
+        }
+
+        assert!(flatfile.storage_size().unwrap() > 0);
+        flatfile.clear().unwrap();
+        assert!(flatfile.get_rune_pool(HOURLY).unwrap_err().is::<NotFound>());
+        assert!(flatfile.get_latest(HOURLY).unwrap_err().is::<NotFound>());
+    }
+
+    #[test]
+    fn legacy_files_become_the_default_dataset() {
+        let dir = TempDir::new().unwrap();
+        let config = fixtures::config(&dir);
+        let expected = dataset(5, 0);
+        FlatFileClient::new(&config).unwrap().update_rune_pool(dataset::LEGACY, &expected).unwrap();
+
+        // Put the files back as the single-dataset layout kept them: one segment and an
+        // unversioned meta, directly under the root.
+        let root = dir.path().join("flatfile");
+        let legacy = root.join(DATASETS_DIR).join(dataset::LEGACY);
+        fs::rename(legacy.join(segment_file(1)), root.join(SEGMENT_FILE)).unwrap();
+        let meta = fs::read(legacy.join(META_FILE)).unwrap();
+        fs::write(root.join(META_FILE), &meta[..UNVERSIONED_META_LEN]).unwrap();
+        fs::remove_dir_all(&legacy).unwrap();
+
+        let flatfile = FlatFileClient::new(&config).unwrap();
+        assert_eq!(flatfile.get_rune_pool(dataset::LEGACY).unwrap(), expected);
+        assert!(!root.join(SEGMENT_FILE).exists() && !root.join(META_FILE).exists());
+    }
+
+    #[test]
+    fn torn_tail_is_dropped_on_reopen() {
+        let dir = TempDir::new().unwrap();
+        let config = fixtures::config(&dir);
+        let expected = dataset(10, 0);
+        FlatFileClient::new(&config).unwrap().update_rune_pool(HOURLY, &expected).unwrap();
+
+        // A length prefix with only half its record behind it, as a crash mid-write leaves.
+        let path = dir.path().join("flatfile").join(DATASETS_DIR).join(HOURLY).join(segment_file(1));
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&(INTERVAL_LEN as u32).to_le_bytes()).unwrap();
+        file.write_all(&[0u8; INTERVAL_LEN / 2]).unwrap();
+
+        let flatfile = FlatFileClient::new(&config).unwrap();
+        assert_eq!(flatfile.get_rune_pool(HOURLY).unwrap(), expected);
+        assert_eq!(flatfile.get_latest(HOURLY).unwrap().as_ref(), expected.intervals.last());
+        assert_eq!(fs::metadata(&path).unwrap().len(), 10 * RECORD_LEN);
+    }
+
+    #[test]
+    fn interrupted_update_keeps_previous_dataset() {
+        let dir = TempDir::new().unwrap();
+        let config = fixtures::config(&dir);
+        let expected = dataset(10, 0);
+        FlatFileClient::new(&config).unwrap().update_rune_pool(HOURLY, &expected).unwrap();
+
+        // The next generation's segment, half written, with no meta committed for it.
+        let dataset_dir = dir.path().join("flatfile").join(DATASETS_DIR).join(HOURLY);
+        fs::write(dataset_dir.join(segment_file(2)), [1u8; 3 * RECORD_LEN as usize / 2]).unwrap();
+        fs::write(dataset_dir.join(format!("{}.tmp", META_FILE)), [1u8; META_LEN / 2]).unwrap();
+
+        let flatfile = FlatFileClient::new(&config).unwrap();
+        assert_eq!(flatfile.get_rune_pool(HOURLY).unwrap(), expected);
+        assert_eq!(flatfile.row_count().unwrap(), 10);
+
+        // The next update overwrites the leftovers and removes the segment it replaces.
+        let replacement = dataset(4, 3600);
+        flatfile.update_rune_pool(HOURLY, &replacement).unwrap();
+        assert_eq!(flatfile.get_rune_pool(HOURLY).unwrap(), replacement);
+        assert!(!dataset_dir.join(segment_file(1)).exists());
+        assert_eq!(flatfile.row_count().unwrap(), 4);
+    }
+
+    #[test]
+    fn unsorted_updates_are_written_in_order() {
+        let dir = TempDir::new().unwrap();
+        let flatfile = FlatFileClient::new(&fixtures::config(&dir)).unwrap();
+        let mut shuffled = dataset(9, 0);
+        shuffled.intervals.reverse();
+        flatfile.update_rune_pool(HOURLY, &shuffled).unwrap();
+        let expected = dataset(9, 0);
+        let (from, to) = (expected.intervals[2].start_time, expected.intervals[6].start_time);
+        assert_eq!(flatfile.get_range(HOURLY, from, to).unwrap(), expected.range(from, to));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::models::series::{FieldKind, Schema};
+use std::error::Error;
+
+/// Key layout shared by the ordered key-value stores: `{table}/{dataset}/{scope}/` followed
+/// by the big-endian start time, so one series is a contiguous, time-ordered key range.
+pub fn prefix(table: &str, dataset: &str, scope: &str) -> Vec<u8> {
+    format!("{}/{}/{}/", table, dataset, scope).into_bytes()
+}
+
+/// Prefix of every series in `table`, whatever its dataset and scope.
+pub fn table_prefix(table: &str) -> Vec<u8> {
+    format!("{}/", table).into_bytes()
+}
+
+/// Prefix of every series `dataset` holds in `table`, whatever its scope.
+pub fn dataset_prefix(table: &str, dataset: &str) -> Vec<u8> {
+    format!("{}/{}/", table, dataset).into_bytes()
+}
+
+pub fn key(prefix: &[u8], start_time: u64) -> Vec<u8> {
+    let mut key = prefix.to_vec();
+    key.extend_from_slice(&start_time.to_be_bytes());
+    key
+}
+
+/// `CREATE TABLE` for a series, with one column per field. `u64` and `f64` fields get
+/// `int_type` and `float_type`. Only DuckDB, which has no migrations, creates its tables this
+/// way; Postgres and SQLite get theirs from `migrations/`.
+pub fn create_table(schema: &Schema, int_type: &str, float_type: &str) -> String {
+    let mut columns = vec![
+        "dataset TEXT NOT NULL".to_string(),
+        "scope TEXT NOT NULL".to_string(),
+        format!("start_time {} NOT NULL", int_type),
+        format!("end_time {} NOT NULL", int_type),
+    ];
+    for field in schema.fields {
+        let column_type = match field.kind {
+            FieldKind::U64 => int_type,
+            FieldKind::F64 => float_type,
+        };
+        columns.push(format!("{} {} NOT NULL", field.name, column_type));
+    }
+    columns.push("PRIMARY KEY (dataset, scope, start_time)".to_string());
+    format!("CREATE TABLE IF NOT EXISTS {} ({})", schema.table, columns.join(", "))
+}
+
+/// Every column of a series table: the key columns, then one per field.
+pub fn column_names(schema: &Schema) -> Vec<&'static str> {
+    let mut names = vec!["dataset", "scope", "start_time", "end_time"];
+    names.extend(schema.fields.iter().map(|field| field.name));
+    names
+}
+
+/// Compares the columns a store reports for a series table with its descriptor. Tables
+/// outlive the binary, so a descriptor changed without a migration is caught at startup
+/// instead of failing every write.
+pub fn check_columns(schema: &Schema, actual: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut expected = column_names(schema);
+    expected.sort_unstable();
+    let mut found: Vec<&str> = actual.iter().map(String::as_str).collect();
+    found.sort_unstable();
+    if found != expected {
+        return Err(format!(
+            "Table {} has columns [{}] but the {} series needs [{}]; the table needs a migration",
+            schema.table,
+            found.join(", "),
+            schema.name,
+            expected.join(", ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// `start_time, end_time` and the field columns, in schema order.
+pub fn columns(schema: &Schema) -> String {
+    let mut columns = vec!["start_time", "end_time"];
+    columns.extend(schema.fields.iter().map(|field| field.name));
+    columns.join(", ")
+}
+
+/// `INSERT` binding the dataset as `$1` and the scope as `$2`, then the columns of `columns`
+/// as `$3...`.
+pub fn insert(schema: &Schema) -> String {
+    let placeholders: Vec<String> = (1..=schema.fields.len() + 4).map(|i| format!("${}", i)).collect();
+    format!(
+        "INSERT INTO {} (dataset, scope, {}) VALUES ({})",
+        schema.table,
+        columns(schema),
+        placeholders.join(", ")
+    )
+}
+
+/// Rows in `intervals` and every series table, as one `BIGINT`, for the SQL stores.
+pub fn count_rows() -> String {
+    let counts: Vec<String> = std::iter::once("intervals")
+        .chain(crate::models::series::ALL.iter().map(|schema| schema.table))
+        .map(|table| format!("(SELECT count(*) FROM {})", table))
+        .collect();
+    format!("SELECT CAST({} AS BIGINT)", counts.join(" + "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::series::TVL;
+
+    #[test]
+    fn reports_column_drift() {
+        let mut columns: Vec<String> = column_names(&TVL).into_iter().rev().map(String::from).collect();
+        assert!(check_columns(&TVL, &columns).is_ok());
+
+        columns.retain(|column| column != "rune_price_usd");
+        let error = check_columns(&TVL, &columns).unwrap_err().to_string();
+        assert!(error.starts_with("Table history_tvl has columns"), "{}", error);
+        columns.push("payload".to_string());
+        assert!(check_columns(&TVL, &columns).is_err());
+    }
+}
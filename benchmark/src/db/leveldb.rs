@@ -1,14 +1,43 @@
 use crate::config::Config;
-use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
+use crate::error::NotFound;
+use crate::db::codec::Codec;
+use crate::db::dataset;
+use crate::db::history;
+use crate::db::storage::{dir_size, row_prefixes};
+use crate::models::series::{self, Record, Schema};
+use crate::models::rune_pool::DbRunePoolResponse;
+use db_key::Key;
+use leveldb::batch::{Batch, Writebatch};
 use leveldb::database::Database;
+use leveldb::iterator::{Iterable, LevelDBIterator};
 use leveldb::kv::KV;
 use leveldb::options::{Options, ReadOptions, WriteOptions};
-use serde_json;
 use std::error::Error;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Byte-string keys, laid out by `dataset` and `history`.
+struct ByteKey(Vec<u8>);
+
+impl Key for ByteKey {
+    fn from_u8(key: &[u8]) -> Self {
+        ByteKey(key.to_vec())
+    }
+
+    fn as_slice<T, F: Fn(&[u8]) -> T>(&self, f: F) -> T {
+        f(&self.0)
+    }
+}
+
+/// The single-dataset layout keyed the meta as `0i32` and intervals as `1i32..`, big-endian,
+/// so its keys are four bytes starting with a zero byte and sort before every current key.
+fn is_legacy_key(key: &[u8]) -> bool {
+    key.len() == 4 && key[0] == 0
+}
 
 pub struct LevelDBClient {
-    db: Database<i32>, 
+    db: Database<ByteKey>,
+    codec: Codec,
+    path: PathBuf,
 }
 
 impl LevelDBClient {
@@ -16,70 +45,156 @@ impl LevelDBClient {
         let mut opts = Options::new();
         opts.create_if_missing = true;
         let db = Database::open(Path::new(&config.leveldb_path), opts)?;
-        Ok(LevelDBClient { db })
+        let codec = Codec::parse(&config.kv_codec)?;
+        let client = LevelDBClient { db, codec, path: PathBuf::from(&config.leveldb_path) };
+        client.migrate_legacy_keys()?;
+        Ok(client)
     }
 
-    pub fn update_rune_pool(&self, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
-        let write_opts = WriteOptions::new();
-        let meta_key = 0; 
-        let meta_value = serde_json::to_vec(&response.meta)?;
-        self.db.put(write_opts, meta_key, &meta_value)?;
-
-        let write_opts = WriteOptions::new(); 
-        for (index, interval) in response.intervals.iter().enumerate() {
-            let key = index as i32 + 1; 
-            let value = serde_json::to_vec(interval)?;
-            self.db.put(write_opts, key, &value)?;
+    /// Moves the single-dataset keys into the `default` dataset, unless it already exists,
+    /// and deletes them, all in one batch.
+    fn migrate_legacy_keys(&self) -> Result<(), Box<dyn Error>> {
+        let legacy: Vec<(ByteKey, Vec<u8>)> =
+            self.db.iter(ReadOptions::new()).take_while(|(key, _)| is_legacy_key(&key.0)).collect();
+        if legacy.is_empty() {
+            return Ok(());
         }
-
+        let exists = self.db.get(ReadOptions::new(), ByteKey(dataset::meta_key(dataset::LEGACY)))?.is_some();
+        let mut batch = Writebatch::new();
+        for (key, value) in legacy {
+            if !exists {
+                let moved = if key.0 == [0; 4] {
+                    dataset::meta_key(dataset::LEGACY)
+                } else {
+                    dataset::interval_key(dataset::LEGACY, self.codec.decode_interval(&value)?.start_time)
+                };
+                batch.put(ByteKey(moved), &value);
+            }
+            batch.delete(key);
+        }
+        self.db.write(WriteOptions::new(), &batch)?;
         Ok(())
     }
 
-    pub fn get_rune_pool(&self) -> Result<DbRunePoolResponse, Box<dyn Error>> {
-        let read_opts = ReadOptions::new();
-        let meta_key = 0;
-        let meta_value = self.db.get(read_opts, meta_key)?.ok_or("Meta not found")?;
-        let meta: DbMeta = serde_json::from_slice(&meta_value)?;
+    pub fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        dir_size(&self.path)
+    }
 
+    /// Interval and history keys across every dataset.
+    pub fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        Ok(row_prefixes().iter().map(|prefix| self.scan_prefix(prefix).len() as u64).sum())
+    }
 
-        let mut intervals = Vec::new();
-        let mut index = 1;
-        loop {
-            let read_opts = ReadOptions::new();
-            let key = index as i32;
-            match self.db.get(read_opts, key)? {
-                Some(value) => {
-                    let interval: DbInterval = serde_json::from_slice(&value)?;
-                    intervals.push(interval);
-                    index += 1;
-                }
-                None => break, 
-            }
+    /// Every `(key, value)` starting with `prefix`.
+    fn scan_prefix(&self, prefix: &[u8]) -> Vec<(ByteKey, Vec<u8>)> {
+        let from = ByteKey(prefix.to_vec());
+        self.db
+            .iter(ReadOptions::new())
+            .from(&from)
+            .take_while(|(key, _)| key.0.starts_with(prefix))
+            .collect()
+    }
+
+    pub fn update_series(&self, schema: &Schema, dataset: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+        let prefix = history::prefix(schema.table, dataset, scope);
+        let mut batch = Writebatch::new();
+        for (key, _) in self.scan_prefix(&prefix) {
+            batch.delete(key);
+        }
+        for record in records {
+            let value = self.codec.encode_record(record)?;
+            batch.put(ByteKey(history::key(&prefix, record.start_time)), &value);
         }
+        self.db.write(WriteOptions::new(), &batch)?;
+        Ok(())
+    }
 
-        Ok(DbRunePoolResponse { meta, intervals })
+    pub fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let mut records = Vec::new();
+        for (_, value) in self.scan_prefix(&history::prefix(schema.table, dataset, scope)) {
+            records.push(self.codec.decode_record(schema, &value)?);
+        }
+        if records.is_empty() {
+            return Err(NotFound.into());
+        }
+        Ok(records)
     }
 
+    /// Replaces the dataset's meta and interval range in one batch.
+    pub fn update_rune_pool(&self, name: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let mut batch = Writebatch::new();
+        for (key, _) in self.scan_prefix(&dataset::prefix(name)) {
+            batch.delete(key);
+        }
+        batch.put(ByteKey(dataset::meta_key(name)), &self.codec.encode_meta(&response.meta)?);
+        for interval in &response.intervals {
+            let value = self.codec.encode_interval(interval)?;
+            batch.put(ByteKey(dataset::interval_key(name, interval.start_time)), &value);
+        }
+        self.db.write(WriteOptions::new(), &batch)?;
+        Ok(())
+    }
 
-pub fn clear(&self) -> Result<(), Box<dyn Error>> {
-    let write_opts = WriteOptions::new();
+    pub fn get_rune_pool(&self, name: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let meta_value = self.db.get(ReadOptions::new(), ByteKey(dataset::meta_key(name)))?.ok_or(NotFound)?;
+        let meta = self.codec.decode_meta(&meta_value)?;
 
-    self.db.delete(write_opts, 0)?;
+        let mut intervals = Vec::new();
+        for (_, value) in self.scan_prefix(&dataset::prefix(name)) {
+            intervals.push(self.codec.decode_interval(&value)?);
+        }
+        Ok(DbRunePoolResponse { meta, intervals })
+    }
 
-   
-    let mut index = 1;
-    loop {
-        let read_opts = ReadOptions::new(); 
-        let key = index as i32;
-        if self.db.get(read_opts, key)?.is_some() {
-            self.db.delete(write_opts, key)?;
-            index += 1;
-        } else {
-            break;
+    /// Deletes the dataset's meta, intervals and history in one batch.
+    pub fn clear_dataset(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        let mut batch = Writebatch::new();
+        batch.delete(ByteKey(dataset::meta_key(name)));
+        let prefixes = series::ALL.iter().map(|schema| history::dataset_prefix(schema.table, name));
+        for prefix in std::iter::once(dataset::prefix(name)).chain(prefixes) {
+            for (key, _) in self.scan_prefix(&prefix) {
+                batch.delete(key);
+            }
         }
+        self.db.write(WriteOptions::new(), &batch)?;
+        Ok(())
     }
 
-    Ok(())
+    /// Deletes every key, including any left by older layouts.
+    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
+        let mut batch = Writebatch::new();
+        for key in self.db.keys_iter(ReadOptions::new()) {
+            batch.delete(key);
+        }
+        self.db.write(WriteOptions::new(), &batch)?;
+        Ok(())
+    }
 }
 
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures;
+    use tempfile::TempDir;
+
+    #[test]
+    fn legacy_keys_become_the_default_dataset() {
+        let dir = TempDir::new().unwrap();
+        let config = fixtures::config(&dir);
+        let data = fixtures::dataset(6, 0);
+        {
+            let leveldb = LevelDBClient::new(&config).unwrap();
+            let mut batch = Writebatch::new();
+            batch.put(ByteKey(0i32.to_be_bytes().to_vec()), &leveldb.codec.encode_meta(&data.meta).unwrap());
+            for (index, interval) in data.intervals.iter().enumerate() {
+                let key = ByteKey((index as i32 + 1).to_be_bytes().to_vec());
+                batch.put(key, &leveldb.codec.encode_interval(interval).unwrap());
+            }
+            leveldb.db.write(WriteOptions::new(), &batch).unwrap();
+        }
+
+        let leveldb = LevelDBClient::new(&config).unwrap();
+        assert_eq!(leveldb.get_rune_pool(dataset::LEGACY).unwrap(), data);
+        assert!(!leveldb.db.keys_iter(ReadOptions::new()).any(|key| is_legacy_key(&key.0)));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::config::Config;
+use crate::db::codec::Codec;
+use crate::db::dataset;
+use crate::db::history;
+use crate::error::NotFound;
+use crate::models::series::{self, Record, Schema};
+use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
+use heed::byteorder::BigEndian;
+use heed::types::{Bytes, SerdeJson, Str, U64};
+use heed::{Database, Env, EnvOpenOptions, RwTxn};
+use std::error::Error;
+
+pub struct LmdbClient {
+    env: Env,
+    /// Keyed by dataset name.
+    meta: Database<Str, Bytes>,
+    /// Keyed by `dataset::interval_key`.
+    intervals: Database<Bytes, Bytes>,
+    history: Database<Bytes, Bytes>,
+    codec: Codec,
+}
+
+impl LmdbClient {
+    pub fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
+        std::fs::create_dir_all(&config.lmdb_path)?;
+        // Safety: the environment is opened once per process and the files are not
+        // modified by anything else while it is mapped.
+        let env = unsafe {
+            EnvOpenOptions::new()
+                .map_size(config.lmdb_map_size)
+                .max_dbs(5)
+                .open(&config.lmdb_path)?
+        };
+
+        let mut wtxn = env.write_txn()?;
+        let meta = env.create_database(&mut wtxn, Some("dataset_meta"))?;
+        let intervals = env.create_database(&mut wtxn, Some("dataset_intervals"))?;
+        let history = env.create_database(&mut wtxn, Some("history"))?;
+        let codec = Codec::parse(&config.kv_codec)?;
+        let client = LmdbClient { env: env.clone(), meta, intervals, history, codec };
+        client.migrate_legacy_databases(&mut wtxn)?;
+        wtxn.commit()?;
+        Ok(client)
+    }
+
+    /// "meta" and "intervals" hold the single-dataset layout, as JSON under the key "meta"
+    /// and big-endian start times. They are re-encoded into the `default` dataset, unless it
+    /// already exists, and emptied in the same write transaction.
+    fn migrate_legacy_databases(&self, wtxn: &mut RwTxn) -> Result<(), Box<dyn Error>> {
+        let legacy_meta = self.env.open_database::<Str, SerdeJson<DbMeta>>(wtxn, Some("meta"))?;
+        let legacy_intervals = self.env.open_database::<U64<BigEndian>, SerdeJson<DbInterval>>(wtxn, Some("intervals"))?;
+        if let Some(db) = legacy_meta {
+            let meta = db.get(wtxn, "meta")?;
+            if let (Some(meta), None) = (meta, self.meta.get(wtxn, dataset::LEGACY)?) {
+                self.meta.put(wtxn, dataset::LEGACY, &self.codec.encode_meta(&meta)?)?;
+                let mut moved = Vec::new();
+                if let Some(db) = legacy_intervals {
+                    for entry in db.iter(wtxn)? {
+                        let (_, interval) = entry?;
+                        moved.push(interval);
+                    }
+                }
+                for interval in &moved {
+                    let key = dataset::interval_key(dataset::LEGACY, interval.start_time);
+                    self.intervals.put(wtxn, &key, &self.codec.encode_interval(interval)?)?;
+                }
+            }
+            db.clear(wtxn)?;
+        }
+        if let Some(db) = legacy_intervals {
+            db.clear(wtxn)?;
+        }
+        Ok(())
+    }
+
+    pub fn update_rune_pool(&self, name: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let mut wtxn = self.env.write_txn()?;
+        self.meta.put(&mut wtxn, name, &self.codec.encode_meta(&response.meta)?)?;
+        let mut stale = Vec::new();
+        for entry in self.intervals.prefix_iter(&wtxn, &dataset::prefix(name))? {
+            let (key, _) = entry?;
+            stale.push(key.to_vec());
+        }
+        for key in &stale {
+            self.intervals.delete(&mut wtxn, key)?;
+        }
+        for interval in &response.intervals {
+            let value = self.codec.encode_interval(interval)?;
+            self.intervals.put(&mut wtxn, &dataset::interval_key(name, interval.start_time), &value)?;
+        }
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    pub fn get_rune_pool(&self, name: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let rtxn = self.env.read_txn()?;
+        let meta = self.codec.decode_meta(self.meta.get(&rtxn, name)?.ok_or(NotFound)?)?;
+
+        let mut intervals = Vec::new();
+        for entry in self.intervals.prefix_iter(&rtxn, &dataset::prefix(name))? {
+            let (_, value) = entry?;
+            intervals.push(self.codec.decode_interval(value)?);
+        }
+        Ok(DbRunePoolResponse { meta, intervals })
+    }
+
+    pub fn update_series(&self, schema: &Schema, dataset: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+        let prefix = history::prefix(schema.table, dataset, scope);
+        let mut wtxn = self.env.write_txn()?;
+        let mut stale = Vec::new();
+        for entry in self.history.prefix_iter(&wtxn, &prefix)? {
+            let (key, _) = entry?;
+            stale.push(key.to_vec());
+        }
+        for key in &stale {
+            self.history.delete(&mut wtxn, key)?;
+        }
+        for record in records {
+            self.history.put(&mut wtxn, &history::key(&prefix, record.start_time), &self.codec.encode_record(record)?)?;
+        }
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    pub fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let rtxn = self.env.read_txn()?;
+        let mut records = Vec::new();
+        for entry in self.history.prefix_iter(&rtxn, &history::prefix(schema.table, dataset, scope))? {
+            let (_, value) = entry?;
+            records.push(self.codec.decode_record(schema, value)?);
+        }
+        if records.is_empty() {
+            return Err(NotFound.into());
+        }
+        Ok(records)
+    }
+
+    pub fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        Ok(self.env.real_disk_size()?)
+    }
+
+    /// Interval and history entries across every dataset.
+    pub fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let rtxn = self.env.read_txn()?;
+        Ok(self.intervals.len(&rtxn)? + self.history.len(&rtxn)?)
+    }
+
+    pub fn clear_dataset(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        let mut wtxn = self.env.write_txn()?;
+        self.meta.delete(&mut wtxn, name)?;
+        let mut stale = Vec::new();
+        for entry in self.intervals.prefix_iter(&wtxn, &dataset::prefix(name))? {
+            let (key, _) = entry?;
+            stale.push(key.to_vec());
+        }
+        for key in &stale {
+            self.intervals.delete(&mut wtxn, key)?;
+        }
+        let mut stale = Vec::new();
+        for schema in series::ALL {
+            for entry in self.history.prefix_iter(&wtxn, &history::dataset_prefix(schema.table, name))? {
+                let (key, _) = entry?;
+                stale.push(key.to_vec());
+            }
+        }
+        for key in &stale {
+            self.history.delete(&mut wtxn, key)?;
+        }
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
+        let mut wtxn = self.env.write_txn()?;
+        self.meta.clear(&mut wtxn)?;
+        self.intervals.clear(&mut wtxn)?;
+        self.history.clear(&mut wtxn)?;
+        wtxn.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures::{self, HOURLY};
+    use crate::models::series::SWAPS;
+    use tempfile::TempDir;
+
+    #[test]
+    fn legacy_databases_become_the_default_dataset() {
+        let dir = TempDir::new().unwrap();
+        let config = fixtures::config(&dir);
+        let data = fixtures::dataset(6, 0);
+        {
+            let lmdb = LmdbClient::new(&config).unwrap();
+            let mut wtxn = lmdb.env.write_txn().unwrap();
+            let meta = lmdb.env.create_database::<Str, SerdeJson<DbMeta>>(&mut wtxn, Some("meta")).unwrap();
+            meta.put(&mut wtxn, "meta", &data.meta).unwrap();
+            let intervals = lmdb.env.create_database::<U64<BigEndian>, SerdeJson<DbInterval>>(&mut wtxn, Some("intervals")).unwrap();
+            for interval in &data.intervals {
+                intervals.put(&mut wtxn, &interval.start_time, interval).unwrap();
+            }
+            wtxn.commit().unwrap();
+        }
+
+        let lmdb = LmdbClient::new(&config).unwrap();
+        assert_eq!(lmdb.get_rune_pool(dataset::LEGACY).unwrap(), data);
+        let rtxn = lmdb.env.read_txn().unwrap();
+        let intervals = lmdb.env.open_database::<Bytes, Bytes>(&rtxn, Some("intervals")).unwrap().unwrap();
+        assert!(intervals.is_empty(&rtxn).unwrap());
+    }
+
+    #[test]
+    fn codecs_round_trip() {
+        let data = fixtures::dataset(12, 0);
+        let records = fixtures::history(&SWAPS, 3, 0);
+        for codec in fixtures::CODECS {
+            let dir = TempDir::new().unwrap();
+            let mut config = fixtures::config(&dir);
+            config.kv_codec = codec.to_string();
+            let lmdb = LmdbClient::new(&config).unwrap();
+            lmdb.update_rune_pool(HOURLY, &data).unwrap();
+            lmdb.update_series(&SWAPS, HOURLY, "", &records).unwrap();
+            assert_eq!(lmdb.get_rune_pool(HOURLY).unwrap(), data, "{}", codec);
+            assert_eq!(lmdb.get_series(&SWAPS, HOURLY, "").unwrap(), records, "{}", codec);
+        }
+    }
+}
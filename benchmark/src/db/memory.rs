@@ -0,0 +1,351 @@
+use crate::error::NotFound;
+use crate::models::series::{Record, Schema, Value};
+use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::RwLock;
+
+struct Dataset {
+    meta: DbMeta,
+    intervals: BTreeMap<u64, DbInterval>,
+}
+
+#[derive(Default)]
+struct MemoryStore {
+    datasets: BTreeMap<String, Dataset>,
+    /// Records of each series, keyed by `(table, dataset, scope)`.
+    series: BTreeMap<(String, String, String), Vec<Record>>,
+}
+
+/// Zero-I/O store used as the latency baseline and as the reference the other
+/// backends are checked against.
+#[derive(Default)]
+pub struct MemoryClient {
+    store: RwLock<MemoryStore>,
+}
+
+impl MemoryClient {
+    pub fn update_rune_pool(&self, dataset: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let intervals = response
+            .intervals
+            .iter()
+            .map(|interval| (interval.start_time, interval.clone()))
+            .collect();
+
+        let mut store = self.store.write().map_err(|e| e.to_string())?;
+        store.datasets.insert(dataset.to_string(), Dataset { meta: response.meta.clone(), intervals });
+        Ok(())
+    }
+
+    pub fn get_rune_pool(&self, dataset: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let store = self.store.read().map_err(|e| e.to_string())?;
+        let stored = store.datasets.get(dataset).ok_or(NotFound)?;
+        let intervals = stored.intervals.values().cloned().collect();
+        Ok(DbRunePoolResponse { meta: stored.meta.clone(), intervals })
+    }
+
+    /// Interval count of each stored dataset.
+    pub fn dataset_intervals(&self) -> Result<BTreeMap<String, usize>, Box<dyn Error>> {
+        let store = self.store.read().map_err(|e| e.to_string())?;
+        Ok(store.datasets.iter().map(|(name, stored)| (name.clone(), stored.intervals.len())).collect())
+    }
+
+    /// Interval and history records across every dataset.
+    pub fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let store = self.store.read().map_err(|e| e.to_string())?;
+        let intervals: usize = store.datasets.values().map(|stored| stored.intervals.len()).sum();
+        let records: usize = store.series.values().map(Vec::len).sum();
+        Ok((intervals + records) as u64)
+    }
+
+    pub fn update_series(&self, schema: &Schema, dataset: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+        let mut records = records.to_vec();
+        records.sort_by_key(|record| record.start_time);
+        let mut store = self.store.write().map_err(|e| e.to_string())?;
+        store.series.insert((schema.table.to_string(), dataset.to_string(), scope.to_string()), records);
+        Ok(())
+    }
+
+    pub fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let store = self.store.read().map_err(|e| e.to_string())?;
+        let records = store.series.get(&(schema.table.to_string(), dataset.to_string(), scope.to_string()));
+        // Like every other backend, an empty series reads as nothing stored.
+        records.filter(|records| !records.is_empty()).cloned().ok_or_else(|| NotFound.into())
+    }
+
+    /// Approximate heap footprint: the struct sizes plus the map's key per interval,
+    /// and each series record with its values.
+    pub fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        let store = self.store.read().map_err(|e| e.to_string())?;
+        let per_interval = std::mem::size_of::<u64>() + std::mem::size_of::<DbInterval>();
+        let datasets: usize = store
+            .datasets
+            .values()
+            .map(|stored| std::mem::size_of::<DbMeta>() + stored.intervals.len() * per_interval)
+            .sum();
+        let series: usize = store
+            .series
+            .values()
+            .flatten()
+            .map(|record| std::mem::size_of::<Record>() + record.values.len() * std::mem::size_of::<Value>())
+            .sum();
+        Ok((datasets + series) as u64)
+    }
+
+    pub fn clear_dataset(&self, dataset: &str) -> Result<(), Box<dyn Error>> {
+        let mut store = self.store.write().map_err(|e| e.to_string())?;
+        store.datasets.remove(dataset);
+        store.series.retain(|(_, stored, _), _| stored != dataset);
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
+        let mut store = self.store.write().map_err(|e| e.to_string())?;
+        *store = MemoryStore::default();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::duckdb::DuckDbClient;
+    use crate::db::fixtures::{self, dataset, DAILY, HOURLY};
+    use crate::db::flatfile::FlatFileClient;
+    use crate::db::leveldb::LevelDBClient;
+    use crate::db::lmdb::LmdbClient;
+    use crate::db::redb::RedbClient;
+    use crate::db::rocksdb::RocksDBClient;
+    use crate::db::sled::SledClient;
+    use crate::db::sqlite::SqliteClient;
+    use crate::db::surrealdb::SurrealDBClient;
+    use crate::models::series::TVL;
+    use tempfile::TempDir;
+
+    #[test]
+    fn update_replaces_previous_dataset() {
+        let memory = MemoryClient::default();
+        memory.update_rune_pool(HOURLY, &dataset(10, 0)).unwrap();
+        memory.update_rune_pool(HOURLY, &dataset(3, 60)).unwrap();
+        assert_eq!(memory.get_rune_pool(HOURLY).unwrap(), dataset(3, 60));
+    }
+
+    #[test]
+    fn intervals_are_returned_sorted() {
+        let memory = MemoryClient::default();
+        let mut shuffled = dataset(5, 0);
+        shuffled.intervals.reverse();
+        memory.update_rune_pool(HOURLY, &shuffled).unwrap();
+        assert_eq!(memory.get_rune_pool(HOURLY).unwrap(), dataset(5, 0));
+    }
+
+    #[test]
+    fn clear_removes_meta() {
+        let memory = MemoryClient::default();
+        memory.update_rune_pool(HOURLY, &dataset(2, 0)).unwrap();
+        memory.clear().unwrap();
+        assert!(memory.get_rune_pool(HOURLY).is_err());
+    }
+
+    /// The embedded stores, behind one interface so the conformance test drives them alike.
+    enum Store {
+        LevelDb(LevelDBClient),
+        RocksDb(RocksDBClient),
+        Sled(SledClient),
+        Redb(RedbClient),
+        Lmdb(LmdbClient),
+        FlatFile(FlatFileClient),
+        Sqlite(SqliteClient),
+        DuckDb(DuckDbClient),
+        SurrealDb(SurrealDBClient),
+    }
+
+    impl Store {
+        /// Every embedded store rooted in `dir`, RocksDB with and without its prefix extractor.
+        async fn open_all(dir: &TempDir) -> Vec<(&'static str, Store)> {
+            let config = fixtures::config(dir);
+            let mut prefixed = config.clone();
+            prefixed.rocksdb_path = dir.path().join("rocksdb-prefixed").to_string_lossy().into_owned();
+            prefixed.rocksdb_prefix_len = 9;
+            vec![
+                ("leveldb", Store::LevelDb(LevelDBClient::new(&config).unwrap())),
+                ("rocksdb", Store::RocksDb(RocksDBClient::new(&config).unwrap())),
+                ("rocksdb/prefix=9", Store::RocksDb(RocksDBClient::new(&prefixed).unwrap())),
+                ("sled", Store::Sled(SledClient::new(&config).unwrap())),
+                ("redb", Store::Redb(RedbClient::new(&config).unwrap())),
+                ("lmdb", Store::Lmdb(LmdbClient::new(&config).unwrap())),
+                ("flatfile", Store::FlatFile(FlatFileClient::new(&config).unwrap())),
+                ("sqlite", Store::Sqlite(SqliteClient::new(&config).await.unwrap())),
+                ("duckdb", Store::DuckDb(DuckDbClient::new(&config).unwrap())),
+                ("surrealdb", Store::SurrealDb(SurrealDBClient::new(&config).await.unwrap())),
+            ]
+        }
+
+        async fn update_rune_pool(&self, name: &str, data: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+            match self {
+                Store::LevelDb(store) => store.update_rune_pool(name, data),
+                Store::RocksDb(store) => store.update_rune_pool(name, data),
+                Store::Sled(store) => store.update_rune_pool(name, data),
+                Store::Redb(store) => store.update_rune_pool(name, data),
+                Store::Lmdb(store) => store.update_rune_pool(name, data),
+                Store::FlatFile(store) => store.update_rune_pool(name, data),
+                Store::Sqlite(store) => store.update_rune_pool(name, data).await,
+                Store::DuckDb(store) => store.update_rune_pool(name, data),
+                Store::SurrealDb(store) => store.update_rune_pool(name, data).await,
+            }
+        }
+
+        async fn get_rune_pool(&self, name: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+            match self {
+                Store::LevelDb(store) => store.get_rune_pool(name),
+                Store::RocksDb(store) => store.get_rune_pool(name),
+                Store::Sled(store) => store.get_rune_pool(name),
+                Store::Redb(store) => store.get_rune_pool(name),
+                Store::Lmdb(store) => store.get_rune_pool(name),
+                Store::FlatFile(store) => store.get_rune_pool(name),
+                Store::Sqlite(store) => store.get_rune_pool(name).await,
+                Store::DuckDb(store) => store.get_rune_pool(name),
+                Store::SurrealDb(store) => store.get_rune_pool(name, None).await,
+            }
+        }
+
+        async fn update_series(&self, schema: &Schema, name: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+            match self {
+                Store::LevelDb(store) => store.update_series(schema, name, scope, records),
+                Store::RocksDb(store) => store.update_series(schema, name, scope, records),
+                Store::Sled(store) => store.update_series(schema, name, scope, records),
+                Store::Redb(store) => store.update_series(schema, name, scope, records),
+                Store::Lmdb(store) => store.update_series(schema, name, scope, records),
+                Store::FlatFile(store) => store.update_series(schema, name, scope, records),
+                Store::Sqlite(store) => store.update_series(schema, name, scope, records).await,
+                Store::DuckDb(store) => store.update_series(schema, name, scope, records),
+                Store::SurrealDb(store) => store.update_series(schema, name, scope, records).await,
+            }
+        }
+
+        async fn get_series(&self, schema: &Schema, name: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+            match self {
+                Store::LevelDb(store) => store.get_series(schema, name, scope),
+                Store::RocksDb(store) => store.get_series(schema, name, scope),
+                Store::Sled(store) => store.get_series(schema, name, scope),
+                Store::Redb(store) => store.get_series(schema, name, scope),
+                Store::Lmdb(store) => store.get_series(schema, name, scope),
+                Store::FlatFile(store) => store.get_series(schema, name, scope),
+                Store::Sqlite(store) => store.get_series(schema, name, scope).await,
+                Store::DuckDb(store) => store.get_series(schema, name, scope),
+                Store::SurrealDb(store) => store.get_series(schema, name, scope, None).await,
+            }
+        }
+
+        async fn clear_dataset(&self, name: &str) -> Result<(), Box<dyn Error>> {
+            match self {
+                Store::LevelDb(store) => store.clear_dataset(name),
+                Store::RocksDb(store) => store.clear_dataset(name),
+                Store::Sled(store) => store.clear_dataset(name),
+                Store::Redb(store) => store.clear_dataset(name),
+                Store::Lmdb(store) => store.clear_dataset(name),
+                Store::FlatFile(store) => store.clear_dataset(name),
+                Store::Sqlite(store) => store.clear_dataset(name).await,
+                Store::DuckDb(store) => store.clear_dataset(name),
+                Store::SurrealDb(store) => store.clear_dataset(name).await,
+            }
+        }
+
+        async fn clear(&self) -> Result<(), Box<dyn Error>> {
+            match self {
+                Store::LevelDb(store) => store.clear(),
+                Store::RocksDb(store) => store.clear(),
+                Store::Sled(store) => store.clear(),
+                Store::Redb(store) => store.clear(),
+                Store::Lmdb(store) => store.clear(),
+                Store::FlatFile(store) => store.clear(),
+                Store::Sqlite(store) => store.clear().await,
+                Store::DuckDb(store) => store.clear(),
+                Store::SurrealDb(store) => store.clear().await,
+            }
+        }
+
+        async fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+            match self {
+                Store::LevelDb(store) => store.row_count(),
+                Store::RocksDb(store) => store.row_count(),
+                Store::Sled(store) => store.row_count(),
+                Store::Redb(store) => store.row_count(),
+                Store::Lmdb(store) => store.row_count(),
+                Store::FlatFile(store) => store.row_count(),
+                Store::Sqlite(store) => store.row_count().await,
+                Store::DuckDb(store) => store.row_count(),
+                Store::SurrealDb(store) => store.row_count().await,
+            }
+        }
+    }
+
+    /// A read as the comparison sees it: `None` for `NotFound`; any other error fails the test.
+    fn found<T>(label: &str, result: Result<T, Box<dyn Error>>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) if e.is::<NotFound>() => None,
+            Err(e) => panic!("{}: {}", label, e),
+        }
+    }
+
+    /// Asserts that `store` returns what the reference does for every dataset and series
+    /// the fixtures touch, plus a dataset that was never written.
+    async fn assert_matches(memory: &MemoryClient, label: &str, store: &Store, step: &str) {
+        for name in [HOURLY, DAILY, "weekly"] {
+            let expected = found("memory", memory.get_rune_pool(name));
+            let actual = found(label, store.get_rune_pool(name).await);
+            assert_eq!(actual, expected, "{} {} after {}", label, name, step);
+        }
+        let series = fixtures::series().into_iter().map(|(schema, scope, _)| (schema, HOURLY, scope));
+        for (schema, name, scope) in series.chain([(&TVL, DAILY, "")]) {
+            let expected = found("memory", memory.get_series(schema, name, scope));
+            let actual = found(label, store.get_series(schema, name, scope).await);
+            assert_eq!(actual, expected, "{} {}/{}/{} after {}", label, schema.name, name, scope, step);
+        }
+        let rows = store.row_count().await.unwrap_or_else(|e| panic!("{}: {}", label, e));
+        assert_eq!(rows, memory.row_count().unwrap(), "{} rows after {}", label, step);
+    }
+
+    #[tokio::test]
+    async fn embedded_stores_match_reference() {
+        let dir = TempDir::new().unwrap();
+        let memory = MemoryClient::default();
+        let stores = Store::open_all(&dir).await;
+
+        for (name, data) in fixtures::dataset_writes() {
+            memory.update_rune_pool(name, &data).unwrap();
+            for (label, store) in &stores {
+                store.update_rune_pool(name, &data).await.unwrap_or_else(|e| panic!("{}: {}", label, e));
+            }
+        }
+        // Stale records first, so each store must replace a series rather than add to it.
+        let mut writes = Vec::new();
+        for (schema, scope, records) in fixtures::series() {
+            writes.push((schema, HOURLY, scope, fixtures::stale(schema)));
+            writes.push((schema, HOURLY, scope, records));
+        }
+        writes.push((&TVL, DAILY, "", fixtures::history(&TVL, 3, 0)));
+        for (schema, name, scope, records) in &writes {
+            memory.update_series(schema, name, scope, records).unwrap();
+            for (label, store) in &stores {
+                store.update_series(schema, name, scope, records).await.unwrap_or_else(|e| panic!("{}: {}", label, e));
+            }
+        }
+        for (label, store) in &stores {
+            assert_matches(&memory, label, store, "writes").await;
+        }
+
+        memory.clear_dataset(HOURLY).unwrap();
+        for (label, store) in &stores {
+            store.clear_dataset(HOURLY).await.unwrap();
+            assert_matches(&memory, label, store, "clear_dataset").await;
+        }
+
+        memory.clear().unwrap();
+        for (label, store) in &stores {
+            store.clear().await.unwrap();
+            assert_matches(&memory, label, store, "clear").await;
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use sqlx::migrate::Migrator;
+use sqlx::{PgPool, SqlitePool};
+use std::error::Error;
+
+/// Postgres schema, embedded at compile time from `migrations/postgres`.
+/// Applied versions are recorded by sqlx in the `_sqlx_migrations` table.
+pub static POSTGRES: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// SQLite schema, embedded at compile time from `migrations/sqlite`.
+pub static SQLITE: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+pub async fn run_postgres(pool: &PgPool) -> Result<(), Box<dyn Error>> {
+    POSTGRES.run(pool).await?;
+    Ok(())
+}
+
+pub async fn run_sqlite(pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    SQLITE.run(pool).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Row;
+
+    #[tokio::test]
+    async fn datasets_migration_keeps_existing_rows() {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::raw_sql(include_str!("../../migrations/sqlite/20250320000001_create_rune_pool.sql"))
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::raw_sql(
+            "INSERT INTO meta VALUES (0, 7200, 1, 2, 10, 20);
+             INSERT INTO intervals VALUES (0, 3600, 1, 10), (3600, 7200, 2, 20);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::raw_sql(include_str!("../../migrations/sqlite/20250320000003_datasets.sql"))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let meta = sqlx::query("SELECT dataset, end_units FROM meta").fetch_one(&pool).await.unwrap();
+        assert_eq!((meta.get::<String, _>(0), meta.get::<i64, _>(1)), ("default".to_string(), 20));
+        let intervals: Vec<(String, i64)> =
+            sqlx::query_as("SELECT dataset, start_time FROM intervals ORDER BY start_time").fetch_all(&pool).await.unwrap();
+        assert_eq!(intervals, [("default".to_string(), 0), ("default".to_string(), 3600)]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server at PSQL_CONN"]
+    async fn postgres_migrations_keep_inline_schema_rows() {
+        // One connection, so the search path below holds for the migrator too.
+        let conn = crate::config::Config::load().psql_conn;
+        let pool = sqlx::postgres::PgPoolOptions::new().max_connections(1).connect(&conn).await.unwrap();
+        sqlx::raw_sql(
+            "DROP SCHEMA IF EXISTS migration_test CASCADE;
+             CREATE SCHEMA migration_test;
+             SET search_path TO migration_test;
+             CREATE TABLE meta (id SERIAL PRIMARY KEY, start_time BIGINT NOT NULL, end_time BIGINT NOT NULL,
+                 start_count BIGINT NOT NULL, end_count BIGINT NOT NULL, start_units BIGINT NOT NULL, end_units BIGINT NOT NULL);
+             CREATE TABLE intervals (id SERIAL PRIMARY KEY, start_time BIGINT NOT NULL, end_time BIGINT NOT NULL,
+                 count BIGINT NOT NULL, units BIGINT NOT NULL);
+             INSERT INTO meta (start_time, end_time, start_count, end_count, start_units, end_units)
+                 VALUES (0, 3600, 1, 1, 10, 10), (0, 7200, 1, 2, 10, 20);
+             INSERT INTO intervals (start_time, end_time, count, units) VALUES (0, 3600, 9, 9), (0, 3600, 1, 10), (3600, 7200, 2, 20);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        super::run_postgres(&pool).await.unwrap();
+
+        // The latest meta row and the latest interval per start time survive, under `default`.
+        let meta = sqlx::query("SELECT dataset, end_units FROM meta").fetch_all(&pool).await.unwrap();
+        assert_eq!(meta.len(), 1);
+        assert_eq!((meta[0].get::<String, _>(0), meta[0].get::<i64, _>(1)), ("default".to_string(), 20));
+        let intervals: Vec<(String, i64, i64)> =
+            sqlx::query_as("SELECT dataset, start_time, units FROM intervals ORDER BY start_time").fetch_all(&pool).await.unwrap();
+        assert_eq!(intervals, [("default".to_string(), 0, 10), ("default".to_string(), 3600, 20)]);
+        sqlx::query("DROP SCHEMA migration_test CASCADE").execute(&pool).await.unwrap();
+    }
+}
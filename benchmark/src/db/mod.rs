@@ -1,5 +1,21 @@
+pub mod codec;
+pub mod dataset;
 pub mod leveldb;
+pub mod memory;
 pub mod rocksdb;
+pub mod sled;
+pub mod redb;
+pub mod lmdb;
+pub mod flatfile;
+pub mod history;
+pub mod numeric;
 pub mod surrealdb;
 pub mod psql;
-pub mod mongodb;
\ No newline at end of file
+pub mod duckdb;
+pub mod sqlite;
+pub mod storage;
+pub mod mongodb;
+pub mod redis;
+pub mod migrations;
+#[cfg(test)]
+pub mod fixtures;
\ No newline at end of file
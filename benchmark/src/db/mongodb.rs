@@ -1,57 +1,552 @@
 use crate::config::Config;
+use crate::db::dataset;
+use crate::db::numeric;
+use crate::error::NotFound;
+use crate::models::series::{self, FieldKind, Record, Schema, Value};
 use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
-use mongodb::{bson::doc, Client, Collection};
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, ClientSession, Collection, Database, IndexModel};
+use serde::Serialize;
 use std::error::Error;
 
+const META_COLL: &str = "meta";
+const INTERVALS_COLL: &str = "intervals";
+/// Unique index of the meta collection.
+const DATASET_INDEX: &str = "dataset_1_start_time_1";
+/// Unique index of the intervals collection. `Swap` keeps two generations of a dataset,
+/// so the generation is part of the key.
+const GENERATION_INDEX: &str = "dataset_1_generation_1_start_time_1";
+/// Staging collections of the former swap, which renamed whole collections into place.
+const RETIRED_STAGING: [&str; 2] = ["meta_staging", "intervals_staging"];
+/// Unique indexes of the single-dataset layout, which would reject a second dataset.
+const LEGACY_INDEXES: [&str; 2] = ["start_time_1", "scope_1_start_time_1"];
+
+/// How `update_rune_pool` and `update_series` replace what is stored. Every mode only
+/// touches the documents of the dataset being written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaceMode {
+    /// Delete the dataset's documents, then insert. Readers can observe it empty in between.
+    Delete,
+    /// Insert the intervals under a new generation, then point the meta at it with one
+    /// single-document replace. A series has no meta to switch, so it is replaced as with
+    /// `Delete`.
+    Swap,
+    /// Delete and insert inside a multi-document transaction (requires a replica set), for
+    /// series as well as RUNEPool data.
+    Transaction,
+}
+
+impl ReplaceMode {
+    pub fn parse(value: &str) -> Result<Self, Box<dyn Error>> {
+        match value {
+            "delete" => Ok(ReplaceMode::Delete),
+            "swap" => Ok(ReplaceMode::Swap),
+            "transaction" => Ok(ReplaceMode::Transaction),
+            other => Err(format!("Unknown MongoDB replace mode: {}", other).into()),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplaceMode::Delete => "delete",
+            ReplaceMode::Swap => "swap",
+            ReplaceMode::Transaction => "transaction",
+        }
+    }
+}
+
 pub struct MongoDBClient {
+    client: Client,
+    db: Database,
     meta_coll: Collection<DbMeta>,
     intervals_coll: Collection<DbInterval>,
+    replace_mode: ReplaceMode,
+    ordered_inserts: bool,
+    create_indexes: bool,
+    schema_validation: bool,
+}
+
+fn meta_schema() -> Document {
+    doc! {
+        "$jsonSchema": {
+            "bsonType": "object",
+            "required": ["dataset", "start_time", "end_time", "start_count", "end_count", "start_units", "end_units"],
+            "properties": {
+                "dataset": { "bsonType": "string" },
+                "start_time": { "bsonType": "long", "minimum": 0 },
+                "end_time": { "bsonType": "long", "minimum": 0 },
+                "start_count": { "bsonType": "long", "minimum": 0 },
+                "end_count": { "bsonType": "long", "minimum": 0 },
+                "start_units": { "bsonType": "long", "minimum": 0 },
+                "end_units": { "bsonType": "long", "minimum": 0 },
+                "generation": { "bsonType": "long", "minimum": 1 },
+            }
+        }
+    }
+}
+
+fn interval_schema() -> Document {
+    doc! {
+        "$jsonSchema": {
+            "bsonType": "object",
+            "required": ["dataset", "start_time", "end_time", "count", "units"],
+            "properties": {
+                "dataset": { "bsonType": "string" },
+                "start_time": { "bsonType": "long", "minimum": 0 },
+                "end_time": { "bsonType": "long", "minimum": 0 },
+                "count": { "bsonType": "long", "minimum": 0 },
+                "units": { "bsonType": "long", "minimum": 0 },
+                "generation": { "bsonType": "long", "minimum": 1 },
+            }
+        }
+    }
+}
+
+/// `value` serialized with its dataset name added. `u64` fields become int64, and bson
+/// rejects anything above `i64::MAX` instead of wrapping it.
+fn with_dataset(dataset: &str, value: &impl Serialize) -> Result<Document, Box<dyn Error>> {
+    let mut document = mongodb::bson::to_document(value)?;
+    document.insert("dataset", dataset);
+    Ok(document)
+}
+
+/// `collStats` reports sizes as int32, int64 or double depending on magnitude.
+fn bson_size(stats: &Document, key: &str) -> u64 {
+    match stats.get(key) {
+        Some(Bson::Int32(n)) => *n as u64,
+        Some(Bson::Int64(n)) => *n as u64,
+        Some(Bson::Double(n)) => *n as u64,
+        _ => 0,
+    }
 }
 
 impl MongoDBClient{
     pub async fn new(config : &Config) -> Result<Self, Box<dyn Error>>{
         let client = Client::with_uri_str(&config.mongodb_uri).await?;
         let db   = client.database(&config.db_name);
-        let meta_coll = db.collection::<DbMeta>("meta");
-        let intervals_coll = db.collection::<DbInterval>("intervals");
-        Ok(MongoDBClient {
+        let meta_coll = db.collection::<DbMeta>(META_COLL);
+        let intervals_coll = db.collection::<DbInterval>(INTERVALS_COLL);
+        let replace_mode = ReplaceMode::parse(&config.mongodb_replace_mode)?;
+        let client = MongoDBClient {
+            client,
+            db,
             meta_coll,
             intervals_coll,
-        })
+            replace_mode,
+            ordered_inserts: config.mongodb_ordered_inserts,
+            create_indexes: config.mongodb_create_indexes,
+            schema_validation: config.mongodb_schema_validation,
+        };
+        client.adopt_legacy_documents().await?;
+        client.prepare_collection(META_COLL, meta_schema(), doc! { "dataset": 1, "start_time": 1 }, DATASET_INDEX).await?;
+        let generation_keys = doc! { "dataset": 1, "generation": 1, "start_time": 1 };
+        client.prepare_collection(INTERVALS_COLL, interval_schema(), generation_keys, GENERATION_INDEX).await?;
+        for retired in RETIRED_STAGING.into_iter().chain([series::RETIRED_TABLE]) {
+            client.db.collection::<Document>(retired).drop().await?;
+        }
+        for schema in series::ALL {
+            let coll = client.db.collection::<Document>(schema.table);
+            client.drop_legacy_indexes(&coll).await?;
+            if client.create_indexes {
+                let index = IndexModel::builder()
+                    .keys(doc! { "dataset": 1, "scope": 1, "start_time": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build();
+                coll.create_index(index).await?;
+            }
+        }
+        Ok(client)
     }
 
-    pub async fn update_rune_pool(&self , response : &DbRunePoolResponse)->Result<(),Box<dyn Error>>{
-        self.meta_coll.delete_many(doc! {}).await?;
-        self.intervals_coll.delete_many(doc! {}).await?;
+    /// Documents written before datasets were introduced have no `dataset` field. They join
+    /// the `default` dataset, or are deleted if that dataset already exists.
+    async fn adopt_legacy_documents(&self) -> Result<(), Box<dyn Error>> {
+        let legacy = doc! { "dataset": { "$exists": false } };
+        let exists = self.meta_documents().count_documents(doc! { "dataset": dataset::LEGACY }).await? > 0;
+        let history = series::ALL.iter().map(|schema| schema.table);
+        for name in [META_COLL, INTERVALS_COLL].into_iter().chain(history) {
+            let coll = self.db.collection::<Document>(name);
+            if exists {
+                coll.delete_many(legacy.clone()).await?;
+            } else {
+                coll.update_many(legacy.clone(), doc! { "$set": { "dataset": dataset::LEGACY } }).await?;
+            }
+        }
+        Ok(())
+    }
 
-        self.meta_coll.insert_one(&response.meta).await?;
+    async fn drop_legacy_indexes(&self, coll: &Collection<Document>) -> Result<(), Box<dyn Error>> {
+        let exists = self.db.list_collection_names().await?.iter().any(|n| n == coll.name());
+        if exists {
+            for name in coll.list_index_names().await? {
+                if LEGACY_INDEXES.contains(&name.as_str()) {
+                    coll.drop_index(name).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Brings a collection in line with the configured validator and its unique `index`, dropping
+    /// the other unique index it may carry from an earlier layout. Both settings are applied in
+    /// either direction so runs with and without them compare cleanly.
+    async fn prepare_collection(&self, name: &str, schema: Document, keys: Document, index: &str) -> Result<(), Box<dyn Error>> {
+        let exists = self.db.list_collection_names().await?.iter().any(|n| n == name);
+        let validator = if self.schema_validation { schema } else { doc! {} };
+
+        if exists {
+            self.db
+                .run_command(doc! { "collMod": name, "validator": validator })
+                .await?;
+        } else if self.schema_validation {
+            self.db.create_collection(name).validator(validator).await?;
+        }
+
+        let coll = self.db.collection::<Document>(name);
+        self.drop_legacy_indexes(&coll).await?;
+        let existing = if exists { coll.list_index_names().await? } else { Vec::new() };
+        for stale in [DATASET_INDEX, GENERATION_INDEX] {
+            if (stale != index || !self.create_indexes) && existing.iter().any(|n| n == stale) {
+                coll.drop_index(stale).await?;
+            }
+        }
+        if self.create_indexes {
+            let model = IndexModel::builder()
+                .keys(keys)
+                .options(IndexOptions::builder().name(index.to_string()).unique(true).build())
+                .build();
+            coll.create_index(model).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Label for the active write strategy, reported next to the timings.
+    pub fn write_mode(&self) -> String {
+        let ordering = if self.ordered_inserts { "ordered" } else { "unordered" };
+        format!("{}/{}", self.replace_mode.as_str(), ordering)
+    }
+
+    pub async fn update_rune_pool(&self, dataset: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let meta = with_dataset(dataset, &response.meta)?;
+        let intervals = response
+            .intervals
+            .iter()
+            .map(|interval| with_dataset(dataset, interval))
+            .collect::<Result<Vec<_>, _>>()?;
+        match self.replace_mode {
+            ReplaceMode::Delete => self.replace_by_delete(dataset, meta, intervals).await,
+            ReplaceMode::Swap => self.replace_by_swap(dataset, meta, intervals).await,
+            ReplaceMode::Transaction => self.replace_in_transaction(dataset, meta, intervals).await,
+        }
+    }
+
+    fn meta_documents(&self) -> Collection<Document> {
+        self.meta_coll.clone_with_type()
+    }
+
+    fn interval_documents(&self) -> Collection<Document> {
+        self.intervals_coll.clone_with_type()
+    }
+
+    async fn replace_by_delete(&self, dataset: &str, meta: Document, intervals: Vec<Document>) -> Result<(), Box<dyn Error>> {
+        self.meta_documents().delete_many(doc! { "dataset": dataset }).await?;
+        self.interval_documents().delete_many(doc! { "dataset": dataset }).await?;
+
+        self.meta_documents().insert_one(meta).await?;
+
+        if !intervals.is_empty() {
+            self.interval_documents()
+                .insert_many(intervals)
+                .ordered(self.ordered_inserts)
+                .await?;
+        }
 
-        self.intervals_coll.insert_many(response.intervals.clone())
-        .await?;
-    
         Ok(())
     }
 
-    pub async fn get_rune_pool(&self) -> Result<DbRunePoolResponse, Box<dyn Error>>{
-        let meta = self.meta_coll.find_one(doc!{}).await?.ok_or("Meta not found")?;
+    /// Inserts the intervals under the next generation, then points the meta at it with one
+    /// replace, so a reader resolving the generation from the meta gets the old pair or the
+    /// new one. The previous generation is kept until the next swap, so a read that fetched the
+    /// meta just before the switch still finds its intervals.
+    async fn replace_by_swap(&self, dataset: &str, mut meta: Document, mut intervals: Vec<Document>) -> Result<(), Box<dyn Error>> {
+        let current = self.meta_documents().find_one(doc! { "dataset": dataset }).await?;
+        let previous = current.and_then(|meta| meta.get_i64("generation").ok());
+        let generation = previous.map_or(1, |generation| generation + 1);
+
+        // Left behind by a swap that failed before switching the meta.
+        self.interval_documents().delete_many(doc! { "dataset": dataset, "generation": generation }).await?;
+        for interval in &mut intervals {
+            interval.insert("generation", generation);
+        }
+        if !intervals.is_empty() {
+            self.interval_documents()
+                .insert_many(intervals)
+                .ordered(self.ordered_inserts)
+                .await?;
+        }
+
+        meta.insert("generation", generation);
+        self.meta_documents().replace_one(doc! { "dataset": dataset }, meta).upsert(true).await?;
 
-        let mut intervals_cursor = self.intervals_coll.find(doc! {})
+        // Everything but the two live generations; `null` also matches documents written by
+        // the other modes, which carry no generation.
+        let kept = vec![Bson::Int64(generation), previous.map_or(Bson::Null, Bson::Int64)];
+        self.interval_documents()
+            .delete_many(doc! { "dataset": dataset, "generation": { "$nin": kept } })
+            .await?;
+        Ok(())
+    }
+
+    async fn replace_in_transaction(&self, dataset: &str, meta: Document, intervals: Vec<Document>) -> Result<(), Box<dyn Error>> {
+        let mut session = self.client.start_session().await?;
+        session.start_transaction().await?;
+
+        // Stringify before awaiting again: `Box<dyn Error>` is not `Send`.
+        let written = self.write_in_session(&mut session, dataset, meta, intervals).await.map_err(|e| e.to_string());
+        if let Err(message) = written {
+            session.abort_transaction().await?;
+            return Err(message.into());
+        }
+
+        session.commit_transaction().await?;
+        Ok(())
+    }
+
+    async fn write_in_session(
+        &self,
+        session: &mut ClientSession,
+        dataset: &str,
+        meta: Document,
+        intervals: Vec<Document>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.meta_documents().delete_many(doc! { "dataset": dataset }).session(&mut *session).await?;
+        self.interval_documents().delete_many(doc! { "dataset": dataset }).session(&mut *session).await?;
+
+        self.meta_documents().insert_one(meta).session(&mut *session).await?;
+
+        if !intervals.is_empty() {
+            self.interval_documents()
+                .insert_many(intervals)
+                .ordered(self.ordered_inserts)
+                .session(&mut *session)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_rune_pool(&self, dataset: &str) -> Result<DbRunePoolResponse, Box<dyn Error>>{
+        let meta = self.meta_documents().find_one(doc! { "dataset": dataset }).await?.ok_or(NotFound)?;
+        // Only `Swap` writes generations; the other modes leave the field out of both collections.
+        let generation = meta.get_i64("generation").map_or(Bson::Null, Bson::Int64);
+        let meta: DbMeta = mongodb::bson::from_document(meta)?;
+
+        let mut intervals_cursor = self.intervals_coll.find(doc! { "dataset": dataset, "generation": generation })
+        .sort(doc! { "start_time": 1 })
         .await?;
-        
+
         let mut intervals = Vec::new();
 
         while intervals_cursor.advance().await?{
             intervals.push(intervals_cursor.deserialize_current()?);
         }
 
-        intervals.sort_by_key(|i| i.start_time);
-
         Ok(DbRunePoolResponse { meta, intervals })
     }
 
+    /// BSON has no unsigned 64-bit type, so integers go in as checked int64.
+    pub async fn update_series(&self, schema: &Schema, dataset: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+        let mut documents = Vec::with_capacity(records.len());
+        for record in records {
+            let mut document = doc! {
+                "dataset": dataset,
+                "scope": scope,
+                "start_time": numeric::to_i64("start_time", record.start_time)?,
+                "end_time": numeric::to_i64("end_time", record.end_time)?,
+            };
+            for (field, value) in schema.fields.iter().zip(&record.values) {
+                let value = match *value {
+                    Value::U64(n) => Bson::Int64(numeric::to_i64(field.name, n)?),
+                    Value::F64(x) => Bson::Double(x),
+                };
+                document.insert(field.name, value);
+            }
+            documents.push(document);
+        }
+
+        let coll = self.db.collection::<Document>(schema.table);
+        if self.replace_mode != ReplaceMode::Transaction {
+            coll.delete_many(doc! { "dataset": dataset, "scope": scope }).await?;
+            if !documents.is_empty() {
+                coll.insert_many(documents).ordered(self.ordered_inserts).await?;
+            }
+            return Ok(());
+        }
+
+        let mut session = self.client.start_session().await?;
+        session.start_transaction().await?;
+        let written = self.write_series_in_session(&mut session, &coll, dataset, scope, documents).await.map_err(|e| e.to_string());
+        if let Err(message) = written {
+            session.abort_transaction().await?;
+            return Err(message.into());
+        }
+        session.commit_transaction().await?;
+        Ok(())
+    }
+
+    async fn write_series_in_session(
+        &self,
+        session: &mut ClientSession,
+        coll: &Collection<Document>,
+        dataset: &str,
+        scope: &str,
+        documents: Vec<Document>,
+    ) -> Result<(), Box<dyn Error>> {
+        coll.delete_many(doc! { "dataset": dataset, "scope": scope }).session(&mut *session).await?;
+        if !documents.is_empty() {
+            coll.insert_many(documents).ordered(self.ordered_inserts).session(&mut *session).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let mut cursor = self
+            .db
+            .collection::<Document>(schema.table)
+            .find(doc! { "dataset": dataset, "scope": scope })
+            .sort(doc! { "start_time": 1 })
+            .await?;
+        let mut records = Vec::new();
+        while cursor.advance().await? {
+            let document = cursor.deserialize_current()?;
+            let mut values = Vec::with_capacity(schema.fields.len());
+            for field in schema.fields {
+                values.push(match field.kind {
+                    FieldKind::U64 => Value::U64(numeric::to_u64(field.name, document.get_i64(field.name)?)?),
+                    FieldKind::F64 => Value::F64(document.get_f64(field.name)?),
+                });
+            }
+            records.push(Record {
+                start_time: numeric::to_u64("start_time", document.get_i64("start_time")?)?,
+                end_time: numeric::to_u64("end_time", document.get_i64("end_time")?)?,
+                values,
+            });
+        }
+        if records.is_empty() {
+            return Err(NotFound.into());
+        }
+        Ok(records)
+    }
+
+    /// `collStats` storage plus index size of every collection.
+    pub async fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        let existing = self.db.list_collection_names().await?;
+        let history = series::ALL.iter().map(|schema| schema.table).filter(|name| existing.iter().any(|n| n == name));
+        let mut total = 0;
+        for name in [META_COLL, INTERVALS_COLL].into_iter().chain(history) {
+            let stats = self.db.run_command(doc! { "collStats": name }).await?;
+            total += bson_size(&stats, "storageSize") + bson_size(&stats, "totalIndexSize");
+        }
+        Ok(total)
+    }
+
+    /// Interval and history documents across every dataset.
+    pub async fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let mut rows = self.intervals_coll.count_documents(doc! {}).await?;
+        for schema in series::ALL {
+            rows += self.db.collection::<Document>(schema.table).count_documents(doc! {}).await?;
+        }
+        Ok(rows)
+    }
+
+    pub async fn clear_dataset(&self, dataset: &str) -> Result<(), Box<dyn Error>> {
+        self.meta_coll.delete_many(doc! { "dataset": dataset }).await?;
+        self.intervals_coll.delete_many(doc! { "dataset": dataset }).await?;
+        for schema in series::ALL {
+            self.db.collection::<Document>(schema.table).delete_many(doc! { "dataset": dataset }).await?;
+        }
+        Ok(())
+    }
+
     pub async fn clear(&self) -> Result<(), Box<dyn Error>> {
         self.meta_coll.delete_many(doc! {}).await?;
         self.intervals_coll.delete_many(doc! {}).await?;
+        for schema in series::ALL {
+            self.db.collection::<Document>(schema.table).delete_many(doc! {}).await?;
+        }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures::{self, DAILY, HOURLY};
+    use crate::models::series::SWAPS;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at MONGODB_URI"]
+    async fn values_past_i64_are_refused() {
+        let dir = TempDir::new().unwrap();
+        let mongodb = MongoDBClient::new(&fixtures::config(&dir)).await.unwrap();
+        let signed = fixtures::extreme(i64::MAX as u64);
+        let records = fixtures::extreme_records(i64::MAX as u64);
+        mongodb.update_rune_pool(HOURLY, &signed).await.unwrap();
+        mongodb.update_series(&SWAPS, HOURLY, "", &records).await.unwrap();
+        assert_eq!(mongodb.get_rune_pool(HOURLY).await.unwrap(), signed);
+        assert_eq!(mongodb.get_series(&SWAPS, HOURLY, "").await.unwrap(), records);
+
+        // Past i64::MAX the write is refused and the store keeps what it had.
+        assert!(mongodb.update_rune_pool(HOURLY, &fixtures::extreme(u64::MAX)).await.is_err());
+        assert!(mongodb.update_series(&SWAPS, HOURLY, "", &fixtures::extreme_records(u64::MAX)).await.is_err());
+        assert_eq!(mongodb.get_rune_pool(HOURLY).await.unwrap(), signed);
+        assert_eq!(mongodb.get_series(&SWAPS, HOURLY, "").await.unwrap(), records);
+        mongodb.clear_dataset(HOURLY).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at MONGODB_URI"]
+    async fn swap_replaces_only_its_dataset() {
+        let dir = TempDir::new().unwrap();
+        let mut config = fixtures::config(&dir);
+        config.mongodb_replace_mode = "swap".to_string();
+        let mongodb = MongoDBClient::new(&config).await.unwrap();
+        let daily = fixtures::dataset(3, 86_400);
+        mongodb.update_rune_pool(DAILY, &daily).await.unwrap();
+        for len in [12, 5, 8] {
+            let hourly = fixtures::dataset(len, 0);
+            mongodb.update_rune_pool(HOURLY, &hourly).await.unwrap();
+            assert_eq!(mongodb.get_rune_pool(HOURLY).await.unwrap(), hourly);
+        }
+        assert_eq!(mongodb.get_rune_pool(DAILY).await.unwrap(), daily);
+
+        // The live generation and the one before it, 8 and 5 intervals.
+        let stored = mongodb.interval_documents().count_documents(doc! { "dataset": HOURLY }).await.unwrap();
+        assert_eq!(stored, 13);
+        mongodb.clear_dataset(HOURLY).await.unwrap();
+        mongodb.clear_dataset(DAILY).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at MONGODB_URI"]
+    async fn legacy_documents_become_the_default_dataset() {
+        let dir = TempDir::new().unwrap();
+        let mut config = fixtures::config(&dir);
+        // The validator would refuse documents without a dataset.
+        config.mongodb_schema_validation = false;
+        let data = fixtures::dataset(6, 0);
+        let mongodb = MongoDBClient::new(&config).await.unwrap();
+        mongodb.clear_dataset(dataset::LEGACY).await.unwrap();
+        let meta = mongodb::bson::to_document(&data.meta).unwrap();
+        let intervals = data.intervals.iter().map(|interval| mongodb::bson::to_document(interval).unwrap());
+        mongodb.meta_documents().insert_one(meta).await.unwrap();
+        mongodb.interval_documents().insert_many(intervals).await.unwrap();
+
+        let mongodb = MongoDBClient::new(&config).await.unwrap();
+        assert_eq!(mongodb.get_rune_pool(dataset::LEGACY).await.unwrap(), data);
+        mongodb.clear_dataset(dataset::LEGACY).await.unwrap();
+    }
+}
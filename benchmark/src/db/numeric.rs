@@ -0,0 +1,52 @@
+use crate::models::rune_pool::{DbInterval, DbMeta};
+
+/// Checked conversions for stores whose integers are signed 64-bit: Postgres `BIGINT`,
+/// SQLite `INTEGER`, BSON int64 and SurrealDB `int`. An `as` cast would silently wrap
+/// anything above `i64::MAX` into a negative number.
+pub fn to_i64(field: &str, value: u64) -> Result<i64, String> {
+    i64::try_from(value).map_err(|_| format!("{} = {} does not fit a signed 64-bit integer", field, value))
+}
+
+/// A stored signed integer read back as `u64`. Negative values can only come from
+/// rows written with a wrapping cast, so they are reported rather than reinterpreted.
+pub fn to_u64(field: &str, value: i64) -> Result<u64, String> {
+    u64::try_from(value).map_err(|_| format!("Stored {} = {} is negative", field, value))
+}
+
+/// `start_time, end_time, start_count, end_count, start_units, end_units`, in column order.
+pub fn meta_row(meta: &DbMeta) -> Result<[i64; 6], String> {
+    Ok([
+        to_i64("start_time", meta.start_time)?,
+        to_i64("end_time", meta.end_time)?,
+        to_i64("start_count", meta.start_count)?,
+        to_i64("end_count", meta.end_count)?,
+        to_i64("start_units", meta.start_units)?,
+        to_i64("end_units", meta.end_units)?,
+    ])
+}
+
+/// `start_time, end_time, count, units`, in column order.
+pub fn interval_row(interval: &DbInterval) -> Result<[i64; 4], String> {
+    Ok([
+        to_i64("start_time", interval.start_time)?,
+        to_i64("end_time", interval.end_time)?,
+        to_i64("count", interval.count)?,
+        to_i64("units", interval.units)?,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_values_outside_i64() {
+        assert_eq!(to_i64("units", i64::MAX as u64), Ok(i64::MAX));
+        assert_eq!(
+            to_i64("units", i64::MAX as u64 + 1),
+            Err("units = 9223372036854775808 does not fit a signed 64-bit integer".to_string())
+        );
+        assert_eq!(to_u64("units", i64::MAX), Ok(i64::MAX as u64));
+        assert!(to_u64("units", -1).is_err());
+    }
+}
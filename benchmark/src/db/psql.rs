@@ -1,8 +1,19 @@
 use crate::config::Config;
+use crate::db::history;
+use crate::db::migrations;
+use crate::db::numeric;
+use crate::error::NotFound;
+use crate::models::series::{self, FieldKind, Record, Schema, Value};
 use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
+use sqlx::postgres::PgRow;
 use sqlx::PgPool;
 use std::error::Error;
 use sqlx::Row;
+/// An unsigned column, stored as a signed 64-bit integer.
+fn uint(row: &PgRow, column: &str) -> Result<u64, Box<dyn Error>> {
+    Ok(numeric::to_u64(column, row.try_get::<i64, _>(column)?)?)
+}
+
 pub struct PsqlClient {
     pool: PgPool,
 }
@@ -10,107 +21,240 @@ pub struct PsqlClient {
 impl PsqlClient {
     pub async fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
         let pool = PgPool::connect(&config.psql_conn).await?;
+        migrations::run_postgres(&pool).await?;
+        for schema in series::ALL {
+            let columns: Vec<String> = sqlx::query_scalar(
+                "SELECT column_name::TEXT FROM information_schema.columns
+                 WHERE table_schema = current_schema() AND table_name = $1",
+            )
+            .bind(schema.table)
+            .fetch_all(&pool)
+            .await?;
+            history::check_columns(schema, &columns)?;
+        }
         Ok(PsqlClient { pool })
     }
 
-    pub async fn update_rune_pool(&self, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS meta (
-                id SERIAL PRIMARY KEY,
-                start_time BIGINT NOT NULL,
-                end_time BIGINT NOT NULL,
-                start_count BIGINT NOT NULL,
-                end_count BIGINT NOT NULL,
-                start_units BIGINT NOT NULL,
-                end_units BIGINT NOT NULL
-            )"
-        )
-        .execute(&self.pool)
-        .await?;
+    pub async fn update_rune_pool(&self, dataset: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        // Convert everything before deleting, so an out-of-range value leaves the old data in place.
+        let meta = numeric::meta_row(&response.meta)?;
+        let intervals = response.intervals.iter().map(numeric::interval_row).collect::<Result<Vec<_>, _>>()?;
 
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS intervals (
-                id SERIAL PRIMARY KEY,
-                start_time BIGINT NOT NULL,
-                end_time BIGINT NOT NULL,
-                count BIGINT NOT NULL,
-                units BIGINT NOT NULL
-            )"
-        )
-        .execute(&self.pool)
-        .await?;
-
-        sqlx::query("DELETE FROM meta").execute(&self.pool).await?;
-        sqlx::query("DELETE FROM intervals").execute(&self.pool).await?;
+        // One transaction, so a failure part-way leaves the previous dataset in place.
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM meta WHERE dataset = $1").bind(dataset).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM intervals WHERE dataset = $1").bind(dataset).execute(&mut *tx).await?;
 
         sqlx::query(
-            "INSERT INTO meta (start_time, end_time, start_count, end_count, start_units, end_units) 
-             VALUES ($1, $2, $3, $4, $5, $6)"
+            "INSERT INTO meta (dataset, start_time, end_time, start_count, end_count, start_units, end_units)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)"
         )
-        .bind(response.meta.start_time as i64)
-        .bind(response.meta.end_time as i64)
-        .bind(response.meta.start_count as i64)
-        .bind(response.meta.end_count as i64)
-        .bind(response.meta.start_units as i64)
-        .bind(response.meta.end_units as i64)
-        .execute(&self.pool)
+        .bind(dataset)
+        .bind(meta[0])
+        .bind(meta[1])
+        .bind(meta[2])
+        .bind(meta[3])
+        .bind(meta[4])
+        .bind(meta[5])
+        .execute(&mut *tx)
         .await?;
 
-        for interval in &response.intervals {
+        for interval in &intervals {
             sqlx::query(
-                "INSERT INTO intervals (start_time, end_time, count, units) 
-                 VALUES ($1, $2, $3, $4)"
+                "INSERT INTO intervals (dataset, start_time, end_time, count, units)
+                 VALUES ($1, $2, $3, $4, $5)"
             )
-            .bind(interval.start_time as i64)
-            .bind(interval.end_time as i64)
-            .bind(interval.count as i64)
-            .bind(interval.units as i64)
-            .execute(&self.pool)
+            .bind(dataset)
+            .bind(interval[0])
+            .bind(interval[1])
+            .bind(interval[2])
+            .bind(interval[3])
+            .execute(&mut *tx)
             .await?;
         }
 
+        tx.commit().await?;
         Ok(())
     }
 
-    pub async fn get_rune_pool(&self) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+    pub async fn get_rune_pool(&self, dataset: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
         let meta_row = sqlx::query(
-            "SELECT start_time, end_time, start_count, end_count, start_units, end_units 
-             FROM meta LIMIT 1"
+            "SELECT start_time, end_time, start_count, end_count, start_units, end_units
+             FROM meta WHERE dataset = $1"
         )
-        .fetch_one(&self.pool)
-        .await?;
+        .bind(dataset)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(NotFound)?;
         let meta = DbMeta {
-            start_time: meta_row.get::<i64, _>("start_time") as u64,
-            end_time: meta_row.get::<i64, _>("end_time") as u64,
-            start_count: meta_row.get::<i64, _>("start_count") as u64,
-            end_count: meta_row.get::<i64, _>("end_count") as u64,
-            start_units: meta_row.get::<i64, _>("start_units") as u64,
-            end_units: meta_row.get::<i64, _>("end_units") as u64,
+            start_time: uint(&meta_row, "start_time")?,
+            end_time: uint(&meta_row, "end_time")?,
+            start_count: uint(&meta_row, "start_count")?,
+            end_count: uint(&meta_row, "end_count")?,
+            start_units: uint(&meta_row, "start_units")?,
+            end_units: uint(&meta_row, "end_units")?,
         };
 
     
         let interval_rows = sqlx::query(
-            "SELECT start_time, end_time, count, units 
-             FROM intervals ORDER BY start_time ASC"
+            "SELECT start_time, end_time, count, units
+             FROM intervals WHERE dataset = $1 ORDER BY start_time ASC"
         )
+        .bind(dataset)
         .fetch_all(&self.pool)
         .await?;
         let intervals = interval_rows
-            .into_iter()
-            .map(|row| DbInterval {
-                start_time: row.get::<i64, _>("start_time") as u64,
-                end_time: row.get::<i64, _>("end_time") as u64,
-                count: row.get::<i64, _>("count") as u64,
-                units: row.get::<i64, _>("units") as u64,
+            .iter()
+            .map(|row| {
+                Ok(DbInterval {
+                    start_time: uint(row, "start_time")?,
+                    end_time: uint(row, "end_time")?,
+                    count: uint(row, "count")?,
+                    units: uint(row, "units")?,
+                })
             })
-            .collect();
+            .collect::<Result<_, Box<dyn Error>>>()?;
 
         Ok(DbRunePoolResponse { meta, intervals })
     }
 
+    pub async fn update_series(
+        &self,
+        schema: &Schema,
+        dataset: &str,
+        scope: &str,
+        records: &[Record],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&format!("DELETE FROM {} WHERE dataset = $1 AND scope = $2", schema.table))
+            .bind(dataset)
+            .bind(scope)
+            .execute(&mut *tx)
+            .await?;
+        let insert = history::insert(schema);
+        for record in records {
+            let mut query = sqlx::query(&insert)
+                .bind(dataset)
+                .bind(scope)
+                .bind(numeric::to_i64("start_time", record.start_time)?)
+                .bind(numeric::to_i64("end_time", record.end_time)?);
+            for (field, value) in schema.fields.iter().zip(&record.values) {
+                query = match *value {
+                    Value::U64(n) => query.bind(numeric::to_i64(field.name, n)?),
+                    Value::F64(x) => query.bind(x),
+                };
+            }
+            query.execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM {} WHERE dataset = $1 AND scope = $2 ORDER BY start_time ASC",
+            history::columns(schema),
+            schema.table
+        ))
+        .bind(dataset)
+        .bind(scope)
+        .fetch_all(&self.pool)
+        .await?;
+        if rows.is_empty() {
+            return Err(NotFound.into());
+        }
+        let mut records = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut values = Vec::with_capacity(schema.fields.len());
+            for field in schema.fields {
+                values.push(match field.kind {
+                    FieldKind::U64 => Value::U64(uint(&row, field.name)?),
+                    FieldKind::F64 => Value::F64(row.try_get::<f64, _>(field.name)?),
+                });
+            }
+            records.push(Record {
+                start_time: uint(&row, "start_time")?,
+                end_time: uint(&row, "end_time")?,
+                values,
+            });
+        }
+        Ok(records)
+    }
+
+    /// Heap, index and TOAST size of every table.
+    pub async fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        let tables: Vec<&str> = ["meta", "intervals"].into_iter().chain(series::ALL.iter().map(|s| s.table)).collect();
+        let size: i64 = sqlx::query_scalar("SELECT sum(pg_total_relation_size(t::regclass))::BIGINT FROM unnest($1::text[]) AS t")
+            .bind(&tables)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(size as u64)
+    }
+
+    /// Interval and history rows across every dataset.
+    pub async fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let rows: i64 = sqlx::query_scalar(&history::count_rows()).fetch_one(&self.pool).await?;
+        Ok(rows as u64)
+    }
+
+    pub async fn clear_dataset(&self, dataset: &str) -> Result<(), Box<dyn Error>> {
+        let tables = ["meta", "intervals"].into_iter().chain(series::ALL.iter().map(|schema| schema.table));
+        for table in tables {
+            sqlx::query(&format!("DELETE FROM {} WHERE dataset = $1", table)).bind(dataset).execute(&self.pool).await?;
+        }
+        Ok(())
+    }
+
     pub async fn clear(&self) -> Result<(), Box<dyn Error>> {
         sqlx::query("DELETE FROM meta").execute(&self.pool).await?;
         sqlx::query("DELETE FROM intervals").execute(&self.pool).await?;
+        for schema in series::ALL {
+            sqlx::query(&format!("DELETE FROM {}", schema.table)).execute(&self.pool).await?;
+        }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures::{self, HOURLY};
+    use crate::models::series::SWAPS;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server at PSQL_CONN"]
+    async fn failed_update_keeps_previous_dataset() {
+        let dir = TempDir::new().unwrap();
+        let psql = PsqlClient::new(&fixtures::config(&dir)).await.unwrap();
+        let data = fixtures::dataset(4, 0);
+        psql.update_rune_pool(HOURLY, &data).await.unwrap();
+
+        // The repeated start time breaks the primary key after the deletes and first inserts ran.
+        let mut duplicate = fixtures::dataset(6, 0);
+        duplicate.intervals[5] = duplicate.intervals[4].clone();
+        assert!(psql.update_rune_pool(HOURLY, &duplicate).await.is_err());
+        assert_eq!(psql.get_rune_pool(HOURLY).await.unwrap(), data);
+        psql.clear_dataset(HOURLY).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server at PSQL_CONN"]
+    async fn values_past_i64_are_refused() {
+        let dir = TempDir::new().unwrap();
+        let psql = PsqlClient::new(&fixtures::config(&dir)).await.unwrap();
+        let signed = fixtures::extreme(i64::MAX as u64);
+        let records = fixtures::extreme_records(i64::MAX as u64);
+        psql.update_rune_pool(HOURLY, &signed).await.unwrap();
+        psql.update_series(&SWAPS, HOURLY, "", &records).await.unwrap();
+        assert_eq!(psql.get_rune_pool(HOURLY).await.unwrap(), signed);
+        assert_eq!(psql.get_series(&SWAPS, HOURLY, "").await.unwrap(), records);
+
+        // Past i64::MAX the write is refused and the store keeps what it had.
+        assert!(psql.update_rune_pool(HOURLY, &fixtures::extreme(u64::MAX)).await.is_err());
+        assert!(psql.update_series(&SWAPS, HOURLY, "", &fixtures::extreme_records(u64::MAX)).await.is_err());
+        assert_eq!(psql.get_rune_pool(HOURLY).await.unwrap(), signed);
+        assert_eq!(psql.get_series(&SWAPS, HOURLY, "").await.unwrap(), records);
+        psql.clear_dataset(HOURLY).await.unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+use crate::config::Config;
+use crate::error::NotFound;
+use crate::db::codec::Codec;
+use crate::db::dataset;
+use crate::db::history;
+use crate::models::series::{self, Record, Schema};
+use crate::models::rune_pool::DbRunePoolResponse;
+use redb::{Database, ReadableTable, ReadableTableMetadata, TableDefinition, WriteTransaction};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Keyed by dataset name.
+const META: TableDefinition<&str, &[u8]> = TableDefinition::new("dataset_meta");
+/// Keyed by `dataset::interval_key`.
+const INTERVALS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("dataset_intervals");
+const HISTORY: TableDefinition<&[u8], &[u8]> = TableDefinition::new("history");
+/// Tables of the single-dataset layout, moved into the `default` dataset on open.
+const LEGACY_META: TableDefinition<&str, &[u8]> = TableDefinition::new("meta");
+const LEGACY_INTERVALS: TableDefinition<u64, &[u8]> = TableDefinition::new("intervals");
+
+/// Copies the single-dataset tables into the `default` dataset, unless it already exists,
+/// and deletes them. Values are copied as stored; only the keys change.
+fn migrate_legacy_tables(txn: &WriteTransaction) -> Result<(), Box<dyn Error>> {
+    {
+        let legacy_meta = txn.open_table(LEGACY_META)?;
+        let legacy_value = legacy_meta.get("meta")?.map(|value| value.value().to_vec());
+        let mut meta = txn.open_table(META)?;
+        let exists = meta.get(dataset::LEGACY)?.is_some();
+        if let (Some(value), false) = (legacy_value, exists) {
+            meta.insert(dataset::LEGACY, value.as_slice())?;
+            let mut intervals = txn.open_table(INTERVALS)?;
+            for entry in txn.open_table(LEGACY_INTERVALS)?.iter()? {
+                let (start_time, value) = entry?;
+                intervals.insert(dataset::interval_key(dataset::LEGACY, start_time.value()).as_slice(), value.value())?;
+            }
+        }
+    }
+    txn.delete_table(LEGACY_META)?;
+    txn.delete_table(LEGACY_INTERVALS)?;
+    Ok(())
+}
+
+pub struct RedbClient {
+    db: Database,
+    codec: Codec,
+    path: PathBuf,
+}
+
+impl RedbClient {
+    pub fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
+        if let Some(parent) = Path::new(&config.redb_path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let db = Database::create(&config.redb_path)?;
+
+        // Create every table up front so read transactions never hit a missing table.
+        let txn = db.begin_write()?;
+        txn.open_table(META)?;
+        txn.open_table(INTERVALS)?;
+        txn.open_table(HISTORY)?;
+        migrate_legacy_tables(&txn)?;
+        txn.commit()?;
+
+        let codec = Codec::parse(&config.kv_codec)?;
+        Ok(RedbClient { db, codec, path: PathBuf::from(&config.redb_path) })
+    }
+
+    pub fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        Ok(std::fs::metadata(&self.path)?.len())
+    }
+
+    /// Interval and history entries across every dataset.
+    pub fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let txn = self.db.begin_read()?;
+        Ok(txn.open_table(INTERVALS)?.len()? + txn.open_table(HISTORY)?.len()?)
+    }
+
+    pub fn update_rune_pool(&self, name: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let prefix = dataset::prefix(name);
+        let txn = self.db.begin_write()?;
+        {
+            let mut meta = txn.open_table(META)?;
+            let meta_value = self.codec.encode_meta(&response.meta)?;
+            meta.insert(name, meta_value.as_slice())?;
+
+            let mut intervals = txn.open_table(INTERVALS)?;
+            intervals.retain_in(prefix.as_slice().., |key, _| !key.starts_with(&prefix))?;
+            for interval in &response.intervals {
+                let value = self.codec.encode_interval(interval)?;
+                intervals.insert(dataset::interval_key(name, interval.start_time).as_slice(), value.as_slice())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    pub fn get_rune_pool(&self, name: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let prefix = dataset::prefix(name);
+        let txn = self.db.begin_read()?;
+
+        let meta_table = txn.open_table(META)?;
+        let meta_value = meta_table.get(name)?.ok_or(NotFound)?;
+        let meta = self.codec.decode_meta(meta_value.value())?;
+
+        let intervals_table = txn.open_table(INTERVALS)?;
+        let mut intervals = Vec::new();
+        for entry in intervals_table.range(prefix.as_slice()..)? {
+            let (key, value) = entry?;
+            if !key.value().starts_with(&prefix) {
+                break;
+            }
+            let interval = self.codec.decode_interval(value.value())?;
+            intervals.push(interval);
+        }
+        Ok(DbRunePoolResponse { meta, intervals })
+    }
+
+    pub fn update_series(&self, schema: &Schema, dataset: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+        let prefix = history::prefix(schema.table, dataset, scope);
+        let txn = self.db.begin_write()?;
+        {
+            let mut stored = txn.open_table(HISTORY)?;
+            let mut stale = Vec::new();
+            for entry in stored.range(prefix.as_slice()..)? {
+                let (key, _) = entry?;
+                if !key.value().starts_with(&prefix) {
+                    break;
+                }
+                stale.push(key.value().to_vec());
+            }
+            for key in &stale {
+                stored.remove(key.as_slice())?;
+            }
+            for record in records {
+                let value = self.codec.encode_record(record)?;
+                stored.insert(history::key(&prefix, record.start_time).as_slice(), value.as_slice())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    pub fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let prefix = history::prefix(schema.table, dataset, scope);
+        let txn = self.db.begin_read()?;
+        let stored = txn.open_table(HISTORY)?;
+        let mut records = Vec::new();
+        for entry in stored.range(prefix.as_slice()..)? {
+            let (key, value) = entry?;
+            if !key.value().starts_with(&prefix) {
+                break;
+            }
+            records.push(self.codec.decode_record(schema, value.value())?);
+        }
+        if records.is_empty() {
+            return Err(NotFound.into());
+        }
+        Ok(records)
+    }
+
+    pub fn clear_dataset(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        let prefix = dataset::prefix(name);
+        let txn = self.db.begin_write()?;
+        txn.open_table(META)?.remove(name)?;
+        txn.open_table(INTERVALS)?.retain_in(prefix.as_slice().., |key, _| !key.starts_with(&prefix))?;
+        {
+            let mut stored = txn.open_table(HISTORY)?;
+            for schema in series::ALL {
+                let prefix = history::dataset_prefix(schema.table, name);
+                stored.retain_in(prefix.as_slice().., |key, _| !key.starts_with(&prefix))?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
+        let txn = self.db.begin_write()?;
+        txn.open_table(META)?.retain(|_, _| false)?;
+        txn.open_table(INTERVALS)?.retain(|_, _| false)?;
+        txn.open_table(HISTORY)?.retain(|_, _| false)?;
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redb::TableHandle;
+    use crate::db::fixtures::{self, HOURLY};
+    use crate::models::series::SWAPS;
+    use tempfile::TempDir;
+
+    #[test]
+    fn legacy_tables_become_the_default_dataset() {
+        let dir = TempDir::new().unwrap();
+        let config = fixtures::config(&dir);
+        let codec = Codec::parse(&config.kv_codec).unwrap();
+        let data = fixtures::dataset(6, 0);
+        {
+            let db = Database::create(&config.redb_path).unwrap();
+            let txn = db.begin_write().unwrap();
+            txn.open_table(LEGACY_META).unwrap().insert("meta", codec.encode_meta(&data.meta).unwrap().as_slice()).unwrap();
+            let mut intervals = txn.open_table(LEGACY_INTERVALS).unwrap();
+            for interval in &data.intervals {
+                intervals.insert(interval.start_time, codec.encode_interval(interval).unwrap().as_slice()).unwrap();
+            }
+            drop(intervals);
+            txn.commit().unwrap();
+        }
+
+        let redb = RedbClient::new(&config).unwrap();
+        assert_eq!(redb.get_rune_pool(dataset::LEGACY).unwrap(), data);
+        let txn = redb.db.begin_read().unwrap();
+        assert!(!txn.list_tables().unwrap().any(|table| table.name() == "intervals"));
+    }
+
+    #[test]
+    fn codecs_round_trip() {
+        let data = fixtures::dataset(12, 0);
+        let records = fixtures::history(&SWAPS, 3, 0);
+        for codec in fixtures::CODECS {
+            let dir = TempDir::new().unwrap();
+            let mut config = fixtures::config(&dir);
+            config.kv_codec = codec.to_string();
+            let redb = RedbClient::new(&config).unwrap();
+            redb.update_rune_pool(HOURLY, &data).unwrap();
+            redb.update_series(&SWAPS, HOURLY, "", &records).unwrap();
+            assert_eq!(redb.get_rune_pool(HOURLY).unwrap(), data, "{}", codec);
+            assert_eq!(redb.get_series(&SWAPS, HOURLY, "").unwrap(), records, "{}", codec);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use crate::config::Config;
+use crate::error::NotFound;
+use crate::models::series::{Record, Schema};
+use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Each dataset keeps its meta hash and interval set under `runepool:{dataset}:`.
+const RUNEPOOL_PATTERN: &str = "runepool:*";
+/// History series live in sorted sets named `history:{table}:{dataset}:{scope}`.
+const HISTORY_PATTERN: &str = "history:*";
+
+fn meta_key(dataset: &str) -> String {
+    format!("runepool:{}:meta", dataset)
+}
+
+fn intervals_key(dataset: &str) -> String {
+    format!("runepool:{}:intervals", dataset)
+}
+
+fn history_key(table: &str, dataset: &str, scope: &str) -> String {
+    format!("history:{}:{}:{}", table, dataset, scope)
+}
+
+pub struct RedisClient {
+    conn: MultiplexedConnection,
+}
+
+impl RedisClient {
+    pub async fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let client = redis::Client::open(config.redis_url.as_str())?;
+        let conn = client.get_multiplexed_async_connection().await?;
+        Ok(RedisClient { conn })
+    }
+
+    /// Replaces meta and intervals in a single MULTI/EXEC pipeline.
+    pub async fn update_rune_pool(&self, dataset: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let meta = &response.meta;
+        let meta_fields = [
+            ("start_time", meta.start_time),
+            ("end_time", meta.end_time),
+            ("start_count", meta.start_count),
+            ("end_count", meta.end_count),
+            ("start_units", meta.start_units),
+            ("end_units", meta.end_units),
+        ];
+
+        // Members are the JSON-encoded intervals, scored by start time.
+        let mut members = Vec::with_capacity(response.intervals.len());
+        for interval in &response.intervals {
+            members.push((interval.start_time, serde_json::to_string(interval)?));
+        }
+
+        let (meta_key, intervals_key) = (meta_key(dataset), intervals_key(dataset));
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .del(&meta_key).ignore()
+            .del(&intervals_key).ignore()
+            .hset_multiple(&meta_key, &meta_fields).ignore();
+        if !members.is_empty() {
+            pipe.zadd_multiple(&intervals_key, &members).ignore();
+        }
+
+        let mut conn = self.conn.clone();
+        pipe.query_async::<()>(&mut conn).await?;
+        Ok(())
+    }
+
+    pub async fn get_rune_pool(&self, dataset: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let meta = self.read_meta(dataset).await?;
+        let intervals = self.intervals_between(dataset, "-inf", "+inf").await?;
+        Ok(DbRunePoolResponse { meta, intervals })
+    }
+
+    /// Intervals with `from <= start_time < to`, selected by score on the server.
+    pub async fn get_range(&self, dataset: &str, from: u64, to: u64) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let meta = self.read_meta(dataset).await?;
+        let intervals = self.intervals_between(dataset, &from.to_string(), &format!("({}", to)).await?;
+        Ok(DbRunePoolResponse { meta, intervals })
+    }
+
+    async fn read_meta(&self, dataset: &str) -> Result<DbMeta, Box<dyn Error>> {
+        let mut conn = self.conn.clone();
+        let fields: HashMap<String, u64> = conn.hgetall(meta_key(dataset)).await?;
+        if fields.is_empty() {
+            return Err(NotFound.into());
+        }
+        let field = |name: &str| fields.get(name).copied().ok_or_else(|| format!("Meta field {} missing", name));
+        Ok(DbMeta {
+            start_time: field("start_time")?,
+            end_time: field("end_time")?,
+            start_count: field("start_count")?,
+            end_count: field("end_count")?,
+            start_units: field("start_units")?,
+            end_units: field("end_units")?,
+        })
+    }
+
+    async fn intervals_between(&self, dataset: &str, min: &str, max: &str) -> Result<Vec<DbInterval>, Box<dyn Error>> {
+        let mut conn = self.conn.clone();
+        let members: Vec<String> = conn.zrangebyscore(intervals_key(dataset), min, max).await?;
+        let mut intervals = Vec::with_capacity(members.len());
+        for member in members {
+            intervals.push(serde_json::from_str(&member)?);
+        }
+        Ok(intervals)
+    }
+
+    /// Replaces one series' sorted set, scored by start time like the RUNEPool intervals.
+    pub async fn update_series(&self, schema: &Schema, dataset: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+        let key = history_key(schema.table, dataset, scope);
+        let mut members = Vec::with_capacity(records.len());
+        for record in records {
+            members.push((record.start_time, serde_json::to_string(record)?));
+        }
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(&key).ignore();
+        if !members.is_empty() {
+            pipe.zadd_multiple(&key, &members).ignore();
+        }
+        let mut conn = self.conn.clone();
+        pipe.query_async::<()>(&mut conn).await?;
+        Ok(())
+    }
+
+    pub async fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let mut conn = self.conn.clone();
+        let members: Vec<String> = conn.zrange(history_key(schema.table, dataset, scope), 0, -1).await?;
+        if members.is_empty() {
+            return Err(NotFound.into());
+        }
+        let mut records = Vec::with_capacity(members.len());
+        for member in members {
+            let record: Record = serde_json::from_str(&member)?;
+            schema.check(&record)?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    /// Every key of every dataset, RUNEPool and history alike.
+    async fn all_keys(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut conn = self.conn.clone();
+        let mut keys = Vec::new();
+        for pattern in [RUNEPOOL_PATTERN, HISTORY_PATTERN] {
+            let mut iter = conn.scan_match::<_, String>(pattern).await?;
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    /// `MEMORY USAGE` of every key, including Redis' per-key overhead.
+    pub async fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        let keys = self.all_keys().await?;
+        let mut conn = self.conn.clone();
+        let mut total = 0;
+        for key in keys {
+            let usage: Option<u64> = redis::cmd("MEMORY").arg("USAGE").arg(key).query_async(&mut conn).await?;
+            total += usage.unwrap_or(0);
+        }
+        Ok(total)
+    }
+
+    /// Members of every interval and history sorted set.
+    pub async fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let mut conn = self.conn.clone();
+        let mut sets = Vec::new();
+        for pattern in [intervals_key("*"), HISTORY_PATTERN.to_string()] {
+            let mut iter = conn.scan_match::<_, String>(pattern).await?;
+            while let Some(key) = iter.next_item().await {
+                sets.push(key);
+            }
+        }
+        let mut rows = 0;
+        for key in sets {
+            rows += conn.zcard::<_, u64>(key).await?;
+        }
+        Ok(rows)
+    }
+
+    pub async fn clear_dataset(&self, dataset: &str) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.conn.clone();
+        let mut keys = vec![meta_key(dataset), intervals_key(dataset)];
+        {
+            let mut iter = conn.scan_match::<_, String>(format!("history:*:{}:*", dataset)).await?;
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+        }
+        conn.del::<_, ()>(&keys).await?;
+        Ok(())
+    }
+
+    pub async fn clear(&self) -> Result<(), Box<dyn Error>> {
+        let keys = self.all_keys().await?;
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.conn.clone();
+        conn.del::<_, ()>(&keys).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures::{self, DAILY, HOURLY};
+    use crate::models::series::SWAPS;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    #[ignore = "needs a redis-server at REDIS_URL"]
+    async fn round_trips_datasets_and_series() {
+        let dir = TempDir::new().unwrap();
+        let redis = RedisClient::new(&fixtures::config(&dir)).await.unwrap();
+        let (hourly, daily) = (fixtures::dataset(12, 0), fixtures::dataset(3, 86_400));
+        let records = fixtures::extreme_records(u64::MAX);
+        redis.update_rune_pool(HOURLY, &hourly).await.unwrap();
+        redis.update_rune_pool(DAILY, &daily).await.unwrap();
+        redis.update_series(&SWAPS, HOURLY, "", &records).await.unwrap();
+        assert_eq!(redis.get_rune_pool(HOURLY).await.unwrap(), hourly);
+        assert_eq!(redis.get_rune_pool(DAILY).await.unwrap(), daily);
+        assert_eq!(redis.get_series(&SWAPS, HOURLY, "").await.unwrap(), records);
+
+        redis.clear_dataset(HOURLY).await.unwrap();
+        assert!(redis.get_rune_pool(HOURLY).await.unwrap_err().is::<NotFound>());
+        assert!(redis.get_series(&SWAPS, HOURLY, "").await.unwrap_err().is::<NotFound>());
+        assert_eq!(redis.get_rune_pool(DAILY).await.unwrap(), daily);
+        redis.clear_dataset(DAILY).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis-server at REDIS_URL"]
+    async fn range_matches_filtering_in_rust() {
+        let dir = TempDir::new().unwrap();
+        let redis = RedisClient::new(&fixtures::config(&dir)).await.unwrap();
+        let data = fixtures::dataset(12, 0);
+        redis.update_rune_pool(HOURLY, &data).await.unwrap();
+
+        let first = data.intervals[0].start_time;
+        let bounds = [(first, first + 3 * 3600), (first + 3600, first + 3600), (0, u64::MAX), (first + 100, first + 7200)];
+        for (from, to) in bounds {
+            assert_eq!(redis.get_range(HOURLY, from, to).await.unwrap(), data.range(from, to), "{}..{}", from, to);
+        }
+        assert!(redis.get_range("missing", 0, u64::MAX).await.unwrap_err().is::<NotFound>());
+        redis.clear_dataset(HOURLY).await.unwrap();
+    }
+}
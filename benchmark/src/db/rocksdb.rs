@@ -1,67 +1,246 @@
 use crate::config::Config;
-use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
-use rocksdb::{Options, DB};
-use serde_json;
+use crate::error::NotFound;
+use crate::db::codec::Codec;
+use crate::db::dataset;
+use crate::db::history;
+use crate::db::storage::{dir_size, row_prefixes};
+use crate::models::series::{self, Record, Schema};
+use crate::models::rune_pool::DbRunePoolResponse;
+use rocksdb::{
+    BlockBasedOptions, Cache, DBCompressionType, Direction, IteratorMode, Options, ReadOptions, SliceTransform,
+    WriteBatch, DB,
+};
 use std::error::Error;
 
+/// Keys of the single-dataset layout: the meta, and intervals numbered by position.
+const LEGACY_META: &[u8] = b"meta";
+const LEGACY_INTERVAL_PREFIX: &[u8] = b"interval_";
+
+/// A raw `(key, value)` pair as the iterator yields it.
+type Entry = (Box<[u8]>, Box<[u8]>);
+
 pub struct RocksDBClient {
     db: DB,
+    codec: Codec,
+    settings: String,
+}
+
+/// Maps a config name to a compression type, rejecting ones whose library isn't compiled in.
+fn compression(name: &str) -> Result<DBCompressionType, Box<dyn Error>> {
+    let (compression, linked) = match name {
+        "none" => (DBCompressionType::None, true),
+        "lz4" => (DBCompressionType::Lz4, cfg!(feature = "rocksdb-lz4")),
+        "snappy" => (DBCompressionType::Snappy, cfg!(feature = "rocksdb-snappy")),
+        "zstd" => (DBCompressionType::Zstd, cfg!(feature = "rocksdb-zstd")),
+        other => return Err(format!("Unknown RocksDB compression: {}", other).into()),
+    };
+    if !linked {
+        return Err(format!("RocksDB compression '{}' needs the rocksdb-{} feature", name, name).into());
+    }
+    Ok(compression)
 }
 
 impl RocksDBClient {
     pub fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
+        opts.set_compression_type(compression(&config.rocksdb_compression)?);
+
+        let mut table_opts = BlockBasedOptions::default();
+        let cache = Cache::new_lru_cache(config.rocksdb_block_cache_mb * 1024 * 1024);
+        table_opts.set_block_cache(&cache);
+        if config.rocksdb_bloom_bits > 0 {
+            table_opts.set_bloom_filter(config.rocksdb_bloom_bits as f64, false);
+        }
+        opts.set_block_based_table_factory(&table_opts);
+
+        // Interval keys share the "interval/" prefix, so 9 groups them all together.
+        if config.rocksdb_prefix_len > 0 {
+            opts.set_prefix_extractor(SliceTransform::create_fixed_prefix(config.rocksdb_prefix_len));
+        }
+
         let db = DB::open(&opts, &config.rocksdb_path)?;
-        Ok(RocksDBClient { db })
+        let codec = Codec::parse(&config.kv_codec)?;
+        let settings = format!(
+            "{}/cache={}MB/bloom={}/prefix={}",
+            config.rocksdb_compression,
+            config.rocksdb_block_cache_mb,
+            config.rocksdb_bloom_bits,
+            config.rocksdb_prefix_len
+        );
+        let client = RocksDBClient { db, codec, settings };
+        client.migrate_legacy_keys()?;
+        Ok(client)
     }
 
-    pub fn update_rune_pool(&self, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
-        let meta_key = "meta".as_bytes();
-        let meta_value = serde_json::to_vec(&response.meta)?;
-        self.db.put(meta_key, meta_value)?;
+    /// Moves the single-dataset keys into the `default` dataset, unless it already exists,
+    /// and deletes them, all in one batch.
+    fn migrate_legacy_keys(&self) -> Result<(), Box<dyn Error>> {
+        let legacy_meta = self.db.get(LEGACY_META)?;
+        let legacy_intervals = self.scan_prefix(LEGACY_INTERVAL_PREFIX)?;
+        if legacy_meta.is_none() && legacy_intervals.is_empty() {
+            return Ok(());
+        }
+        let mut batch = WriteBatch::default();
+        if let (Some(meta), None) = (&legacy_meta, self.db.get(dataset::meta_key(dataset::LEGACY))?) {
+            batch.put(dataset::meta_key(dataset::LEGACY), meta);
+            for (_, value) in &legacy_intervals {
+                let start_time = self.codec.decode_interval(value)?.start_time;
+                batch.put(dataset::interval_key(dataset::LEGACY, start_time), value);
+            }
+        }
+        batch.delete(LEGACY_META);
+        for (key, _) in legacy_intervals {
+            batch.delete(key);
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Label for the active options, reported next to the timings.
+    pub fn settings(&self) -> &str {
+        &self.settings
+    }
+
+    /// Flushes the memtable so compression applies, then sums the files in the data directory.
+    pub fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        self.db.flush()?;
+        dir_size(self.db.path())
+    }
+
+    /// Interval and history keys across every dataset.
+    pub fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let mut rows = 0;
+        for prefix in row_prefixes() {
+            rows += self.scan_prefix(&prefix)?.len() as u64;
+        }
+        Ok(rows)
+    }
 
-        for (index, interval) in response.intervals.iter().enumerate() {
-            let key = format!("interval_{}", index).into_bytes();
-            let value = serde_json::to_vec(interval)?;
-            self.db.put(&key, value)?;
+    /// Replaces the dataset's meta and interval range in one batch.
+    pub fn update_rune_pool(&self, name: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let mut batch = WriteBatch::default();
+        for (key, _) in self.scan_prefix(&dataset::prefix(name))? {
+            batch.delete(key);
+        }
+        batch.put(dataset::meta_key(name), self.codec.encode_meta(&response.meta)?);
+        for interval in &response.intervals {
+            batch.put(dataset::interval_key(name, interval.start_time), self.codec.encode_interval(interval)?);
         }
+        self.db.write(batch)?;
         Ok(())
     }
 
-    pub fn get_rune_pool(&self) -> Result<DbRunePoolResponse, Box<dyn Error>> {
-        let meta_key = "meta".as_bytes();
-        let meta_value = self.db.get(meta_key)?.ok_or("Meta not found")?;
-        let meta: DbMeta = serde_json::from_slice(&meta_value)?;
+    pub fn get_rune_pool(&self, name: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let meta_value = self.db.get(dataset::meta_key(name))?.ok_or(NotFound)?;
+        let meta = self.codec.decode_meta(&meta_value)?;
 
         let mut intervals = Vec::new();
-        let mut index = 0;
-        loop {
-            let key = format!("interval_{}", index).into_bytes();
-            match self.db.get(&key)? {
-                Some(value) => {
-                    let interval: DbInterval = serde_json::from_slice(&value)?;
-                    intervals.push(interval);
-                    index += 1;
-                }
-                None => break,
-            }
+        for (_, value) in self.scan_prefix(&dataset::prefix(name))? {
+            intervals.push(self.codec.decode_interval(&value)?);
         }
         Ok(DbRunePoolResponse { meta, intervals })
     }
 
-    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
-        self.db.delete("meta".as_bytes())?;
-        let mut index = 0;
-        loop {
-            let key = format!("interval_{}", index).into_bytes();
-            if self.db.get(&key)?.is_some() {
-                self.db.delete(&key)?;
-                index += 1;
-            } else {
+    /// Every `(key, value)` under `prefix`. Total-order seek keeps the scan correct
+    /// whatever prefix extractor is configured.
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<Entry>, Box<dyn Error>> {
+        let mut read_opts = ReadOptions::default();
+        read_opts.set_total_order_seek(true);
+        let mut entries = Vec::new();
+        for entry in self.db.iterator_opt(IteratorMode::From(prefix, Direction::Forward), read_opts) {
+            let (key, value) = entry?;
+            if !key.starts_with(prefix) {
                 break;
             }
+            entries.push((key, value));
         }
+        Ok(entries)
+    }
+
+    pub fn update_series(&self, schema: &Schema, dataset: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+        let prefix = history::prefix(schema.table, dataset, scope);
+        let mut batch = WriteBatch::default();
+        for (key, _) in self.scan_prefix(&prefix)? {
+            batch.delete(key);
+        }
+        for record in records {
+            batch.put(history::key(&prefix, record.start_time), self.codec.encode_record(record)?);
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    pub fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let mut records = Vec::new();
+        for (_, value) in self.scan_prefix(&history::prefix(schema.table, dataset, scope))? {
+            records.push(self.codec.decode_record(schema, &value)?);
+        }
+        if records.is_empty() {
+            return Err(NotFound.into());
+        }
+        Ok(records)
+    }
+
+    /// Deletes the dataset's meta, intervals and history in one batch.
+    pub fn clear_dataset(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        let mut batch = WriteBatch::default();
+        batch.delete(dataset::meta_key(name));
+        let prefixes = series::ALL.iter().map(|schema| history::dataset_prefix(schema.table, name));
+        for prefix in std::iter::once(dataset::prefix(name)).chain(prefixes) {
+            for (key, _) in self.scan_prefix(&prefix)? {
+                batch.delete(key);
+            }
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Deletes every key, including any left by older layouts.
+    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
+        let mut batch = WriteBatch::default();
+        for entry in self.db.iterator(IteratorMode::Start) {
+            let (key, _) = entry?;
+            batch.delete(key);
+        }
+        self.db.write(batch)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures;
+    use tempfile::TempDir;
+
+    /// With and without the 9-byte prefix extractor, which groups keys by their first 9 bytes.
+    const PREFIX_LENS: [usize; 2] = [0, 9];
+
+    fn open(dir: &TempDir, prefix_len: usize) -> RocksDBClient {
+        let mut config = fixtures::config(dir);
+        config.rocksdb_prefix_len = prefix_len;
+        RocksDBClient::new(&config).unwrap()
+    }
+
+    #[test]
+    fn legacy_keys_become_the_default_dataset() {
+        for prefix_len in PREFIX_LENS {
+            let dir = TempDir::new().unwrap();
+            let data = fixtures::dataset(12, 0);
+            {
+                let rocksdb = open(&dir, prefix_len);
+                rocksdb.db.put(LEGACY_META, rocksdb.codec.encode_meta(&data.meta).unwrap()).unwrap();
+                for (index, interval) in data.intervals.iter().enumerate() {
+                    let key = format!("interval_{}", index);
+                    rocksdb.db.put(key, rocksdb.codec.encode_interval(interval).unwrap()).unwrap();
+                }
+            }
+
+            let rocksdb = open(&dir, prefix_len);
+            assert_eq!(rocksdb.get_rune_pool(dataset::LEGACY).unwrap(), data, "prefix {}", prefix_len);
+            assert!(rocksdb.db.get(LEGACY_META).unwrap().is_none());
+            assert!(rocksdb.scan_prefix(LEGACY_INTERVAL_PREFIX).unwrap().is_empty());
+        }
+    }
+}
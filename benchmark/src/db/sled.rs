@@ -0,0 +1,204 @@
+use crate::config::Config;
+use crate::error::NotFound;
+use crate::db::codec::Codec;
+use crate::db::dataset;
+use crate::db::history;
+use crate::models::series::{self, Record, Schema};
+use crate::models::rune_pool::DbRunePoolResponse;
+use sled::transaction::TransactionResult;
+use sled::{Batch, Db, Transactional, Tree};
+use std::error::Error;
+
+/// Trees of the single-dataset layout: meta under `b"meta"`, intervals keyed by big-endian start time.
+const LEGACY_META: &str = "meta";
+const LEGACY_INTERVALS: &str = "intervals";
+
+pub struct SledClient {
+    db: Db,
+    meta: Tree,
+    intervals: Tree,
+    history: Tree,
+    codec: Codec,
+}
+
+impl SledClient {
+    pub fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let db = sled::open(&config.sled_path)?;
+        let meta = db.open_tree("dataset_meta")?;
+        let intervals = db.open_tree("dataset_intervals")?;
+        let history = db.open_tree("history")?;
+        let codec = Codec::parse(&config.kv_codec)?;
+        let client = SledClient { db, meta, intervals, history, codec };
+        client.migrate_legacy_trees()?;
+        Ok(client)
+    }
+
+    /// Moves the single-dataset trees into the `default` dataset in one transaction, then
+    /// drops them. If that dataset already exists they are dropped without being copied.
+    fn migrate_legacy_trees(&self) -> Result<(), Box<dyn Error>> {
+        let names = self.db.tree_names();
+        if !names.iter().any(|name| name == LEGACY_META.as_bytes() || name == LEGACY_INTERVALS.as_bytes()) {
+            return Ok(());
+        }
+        let legacy_meta = self.db.open_tree(LEGACY_META)?.get(b"meta")?;
+        if let (Some(meta_value), false) = (legacy_meta, self.meta.contains_key(dataset::LEGACY)?) {
+            let mut moved = Vec::new();
+            for entry in self.db.open_tree(LEGACY_INTERVALS)?.iter() {
+                let (key, value) = entry?;
+                let start_time = u64::from_be_bytes(key.as_ref().try_into()?);
+                moved.push((dataset::interval_key(dataset::LEGACY, start_time), value));
+            }
+            let result: TransactionResult<(), sled::Error> = (&self.meta, &self.intervals).transaction(|(meta, intervals)| {
+                meta.insert(dataset::LEGACY, meta_value.clone())?;
+                for (key, value) in &moved {
+                    intervals.insert(key.as_slice(), value.clone())?;
+                }
+                Ok(())
+            });
+            result?;
+        }
+        self.db.drop_tree(LEGACY_META)?;
+        self.db.drop_tree(LEGACY_INTERVALS)?;
+        Ok(())
+    }
+
+    /// Meta is keyed by dataset name; intervals by `dataset::interval_key`. Both trees change
+    /// in one transaction, so readers never see the new meta with the old intervals.
+    pub fn update_rune_pool(&self, name: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let mut batch = Batch::default();
+        for key in self.intervals.scan_prefix(dataset::prefix(name)).keys() {
+            batch.remove(key?);
+        }
+        for interval in &response.intervals {
+            let value = self.codec.encode_interval(interval)?;
+            batch.insert(dataset::interval_key(name, interval.start_time), value);
+        }
+        let meta_value = self.codec.encode_meta(&response.meta)?;
+
+        let result: TransactionResult<(), sled::Error> = (&self.meta, &self.intervals).transaction(|(meta, intervals)| {
+            intervals.apply_batch(&batch)?;
+            meta.insert(name, meta_value.clone())?;
+            Ok(())
+        });
+        result?;
+        Ok(())
+    }
+
+    pub fn get_rune_pool(&self, name: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let meta_value = self.meta.get(name)?.ok_or(NotFound)?;
+        let meta = self.codec.decode_meta(&meta_value)?;
+
+        let mut intervals = Vec::new();
+        for entry in self.intervals.scan_prefix(dataset::prefix(name)) {
+            let (_, value) = entry?;
+            let interval = self.codec.decode_interval(&value)?;
+            intervals.push(interval);
+        }
+        Ok(DbRunePoolResponse { meta, intervals })
+    }
+
+    /// Replaces one series' key range in a single batch.
+    pub fn update_series(&self, schema: &Schema, dataset: &str, scope: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+        let prefix = history::prefix(schema.table, dataset, scope);
+        let mut batch = Batch::default();
+        for key in self.history.scan_prefix(&prefix).keys() {
+            batch.remove(key?);
+        }
+        for record in records {
+            batch.insert(history::key(&prefix, record.start_time), self.codec.encode_record(record)?);
+        }
+        self.history.apply_batch(batch)?;
+        Ok(())
+    }
+
+    pub fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let mut records = Vec::new();
+        for entry in self.history.scan_prefix(history::prefix(schema.table, dataset, scope)) {
+            let (_, value) = entry?;
+            records.push(self.codec.decode_record(schema, &value)?);
+        }
+        if records.is_empty() {
+            return Err(NotFound.into());
+        }
+        Ok(records)
+    }
+
+    pub fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        self.db.flush()?;
+        Ok(self.db.size_on_disk()?)
+    }
+
+    /// Interval and history entries across every dataset.
+    pub fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        Ok((self.intervals.len() + self.history.len()) as u64)
+    }
+
+    pub fn clear_dataset(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.meta.remove(name)?;
+        let mut batch = Batch::default();
+        for key in self.intervals.scan_prefix(dataset::prefix(name)).keys() {
+            batch.remove(key?);
+        }
+        self.intervals.apply_batch(batch)?;
+        let mut batch = Batch::default();
+        for schema in series::ALL {
+            for key in self.history.scan_prefix(history::dataset_prefix(schema.table, name)).keys() {
+                batch.remove(key?);
+            }
+        }
+        self.history.apply_batch(batch)?;
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
+        self.meta.clear()?;
+        self.intervals.clear()?;
+        self.history.clear()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures::{self, HOURLY};
+    use crate::models::series::SWAPS;
+    use tempfile::TempDir;
+
+    #[test]
+    fn legacy_trees_become_the_default_dataset() {
+        let dir = TempDir::new().unwrap();
+        let config = fixtures::config(&dir);
+        let codec = Codec::parse(&config.kv_codec).unwrap();
+        let data = fixtures::dataset(6, 0);
+        {
+            let db = sled::open(&config.sled_path).unwrap();
+            db.open_tree(LEGACY_META).unwrap().insert(b"meta", codec.encode_meta(&data.meta).unwrap()).unwrap();
+            let intervals = db.open_tree(LEGACY_INTERVALS).unwrap();
+            for interval in &data.intervals {
+                intervals.insert(interval.start_time.to_be_bytes(), codec.encode_interval(interval).unwrap()).unwrap();
+            }
+            db.flush().unwrap();
+        }
+
+        let sled = SledClient::new(&config).unwrap();
+        assert_eq!(sled.get_rune_pool(dataset::LEGACY).unwrap(), data);
+        assert!(!sled.db.tree_names().iter().any(|name| name == LEGACY_INTERVALS.as_bytes()));
+    }
+
+    #[test]
+    fn codecs_round_trip() {
+        let data = fixtures::dataset(12, 0);
+        let records = fixtures::history(&SWAPS, 3, 0);
+        for codec in fixtures::CODECS {
+            let dir = TempDir::new().unwrap();
+            let mut config = fixtures::config(&dir);
+            config.kv_codec = codec.to_string();
+            let sled = SledClient::new(&config).unwrap();
+            sled.update_rune_pool(HOURLY, &data).unwrap();
+            sled.update_series(&SWAPS, HOURLY, "", &records).unwrap();
+            assert_eq!(sled.get_rune_pool(HOURLY).unwrap(), data, "{}", codec);
+            assert_eq!(sled.get_series(&SWAPS, HOURLY, "").unwrap(), records, "{}", codec);
+        }
+    }
+}
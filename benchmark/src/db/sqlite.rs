@@ -0,0 +1,292 @@
+use crate::config::Config;
+use crate::db::history;
+use crate::db::migrations;
+use crate::db::numeric;
+use crate::error::NotFound;
+use crate::models::series::{self, FieldKind, Record, Schema, Value};
+use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow, SqliteSynchronous};
+use sqlx::{Row, SqlitePool};
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+
+/// An unsigned column, stored as a signed 64-bit integer.
+fn uint(row: &SqliteRow, column: &str) -> Result<u64, Box<dyn Error>> {
+    Ok(numeric::to_u64(column, row.try_get::<i64, _>(column)?)?)
+}
+
+pub struct SqliteClient {
+    pool: SqlitePool,
+}
+
+/// Fails when a history table's columns no longer match its descriptor.
+async fn check_history_columns(pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    for schema in series::ALL {
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info($1)").bind(schema.table).fetch_all(pool).await?;
+        history::check_columns(schema, &columns)?;
+    }
+    Ok(())
+}
+
+impl SqliteClient {
+    pub async fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let in_memory = config.sqlite_path == ":memory:";
+        let options = if in_memory {
+            SqliteConnectOptions::from_str("sqlite::memory:")?
+        } else {
+            if let Some(parent) = Path::new(&config.sqlite_path).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            SqliteConnectOptions::new()
+                .filename(&config.sqlite_path)
+                .create_if_missing(true)
+        };
+        let options = options
+            .journal_mode(SqliteJournalMode::from_str(&config.sqlite_journal_mode)?)
+            .synchronous(SqliteSynchronous::from_str(&config.sqlite_synchronous)?);
+
+        // Every connection to `:memory:` is its own database, so keep exactly one alive.
+        let pool_options = if in_memory {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .min_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new()
+        };
+        let pool = pool_options.connect_with(options).await?;
+        migrations::run_sqlite(&pool).await?;
+        check_history_columns(&pool).await?;
+        Ok(SqliteClient { pool })
+    }
+
+    pub async fn update_rune_pool(&self, dataset: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        // Convert everything before deleting, so an out-of-range value leaves the old data in place.
+        let meta = numeric::meta_row(&response.meta)?;
+        let intervals = response.intervals.iter().map(numeric::interval_row).collect::<Result<Vec<_>, _>>()?;
+
+        // One transaction, so a failure part-way leaves the previous dataset in place.
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM meta WHERE dataset = $1").bind(dataset).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM intervals WHERE dataset = $1").bind(dataset).execute(&mut *tx).await?;
+
+        sqlx::query(
+            "INSERT INTO meta (dataset, start_time, end_time, start_count, end_count, start_units, end_units)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        )
+        .bind(dataset)
+        .bind(meta[0])
+        .bind(meta[1])
+        .bind(meta[2])
+        .bind(meta[3])
+        .bind(meta[4])
+        .bind(meta[5])
+        .execute(&mut *tx)
+        .await?;
+
+        for interval in &intervals {
+            sqlx::query(
+                "INSERT INTO intervals (dataset, start_time, end_time, count, units)
+                 VALUES ($1, $2, $3, $4, $5)"
+            )
+            .bind(dataset)
+            .bind(interval[0])
+            .bind(interval[1])
+            .bind(interval[2])
+            .bind(interval[3])
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_rune_pool(&self, dataset: &str) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let meta_row = sqlx::query(
+            "SELECT start_time, end_time, start_count, end_count, start_units, end_units
+             FROM meta WHERE dataset = $1"
+        )
+        .bind(dataset)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(NotFound)?;
+        let meta = DbMeta {
+            start_time: uint(&meta_row, "start_time")?,
+            end_time: uint(&meta_row, "end_time")?,
+            start_count: uint(&meta_row, "start_count")?,
+            end_count: uint(&meta_row, "end_count")?,
+            start_units: uint(&meta_row, "start_units")?,
+            end_units: uint(&meta_row, "end_units")?,
+        };
+
+        let interval_rows = sqlx::query(
+            "SELECT start_time, end_time, count, units
+             FROM intervals WHERE dataset = $1 ORDER BY start_time ASC"
+        )
+        .bind(dataset)
+        .fetch_all(&self.pool)
+        .await?;
+        let intervals = interval_rows
+            .iter()
+            .map(|row| {
+                Ok(DbInterval {
+                    start_time: uint(row, "start_time")?,
+                    end_time: uint(row, "end_time")?,
+                    count: uint(row, "count")?,
+                    units: uint(row, "units")?,
+                })
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+
+        Ok(DbRunePoolResponse { meta, intervals })
+    }
+
+    pub async fn update_series(
+        &self,
+        schema: &Schema,
+        dataset: &str,
+        scope: &str,
+        records: &[Record],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&format!("DELETE FROM {} WHERE dataset = $1 AND scope = $2", schema.table))
+            .bind(dataset)
+            .bind(scope)
+            .execute(&mut *tx)
+            .await?;
+        let insert = history::insert(schema);
+        for record in records {
+            let mut query = sqlx::query(&insert)
+                .bind(dataset)
+                .bind(scope)
+                .bind(numeric::to_i64("start_time", record.start_time)?)
+                .bind(numeric::to_i64("end_time", record.end_time)?);
+            for (field, value) in schema.fields.iter().zip(&record.values) {
+                query = match *value {
+                    Value::U64(n) => query.bind(numeric::to_i64(field.name, n)?),
+                    Value::F64(x) => query.bind(x),
+                };
+            }
+            query.execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_series(&self, schema: &Schema, dataset: &str, scope: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM {} WHERE dataset = $1 AND scope = $2 ORDER BY start_time ASC",
+            history::columns(schema),
+            schema.table
+        ))
+        .bind(dataset)
+        .bind(scope)
+        .fetch_all(&self.pool)
+        .await?;
+        if rows.is_empty() {
+            return Err(NotFound.into());
+        }
+        let mut records = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut values = Vec::with_capacity(schema.fields.len());
+            for field in schema.fields {
+                values.push(match field.kind {
+                    FieldKind::U64 => Value::U64(uint(&row, field.name)?),
+                    FieldKind::F64 => Value::F64(row.try_get::<f64, _>(field.name)?),
+                });
+            }
+            records.push(Record {
+                start_time: uint(&row, "start_time")?,
+                end_time: uint(&row, "end_time")?,
+                values,
+            });
+        }
+        Ok(records)
+    }
+
+    pub async fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        let size: i64 = sqlx::query_scalar(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(size as u64)
+    }
+
+    /// Interval and history rows across every dataset.
+    pub async fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let rows: i64 = sqlx::query_scalar(&history::count_rows()).fetch_one(&self.pool).await?;
+        Ok(rows as u64)
+    }
+
+    pub async fn clear_dataset(&self, dataset: &str) -> Result<(), Box<dyn Error>> {
+        let tables = ["meta", "intervals"].into_iter().chain(series::ALL.iter().map(|schema| schema.table));
+        for table in tables {
+            sqlx::query(&format!("DELETE FROM {} WHERE dataset = $1", table)).bind(dataset).execute(&self.pool).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn clear(&self) -> Result<(), Box<dyn Error>> {
+        sqlx::query("DELETE FROM meta").execute(&self.pool).await?;
+        sqlx::query("DELETE FROM intervals").execute(&self.pool).await?;
+        for schema in series::ALL {
+            sqlx::query(&format!("DELETE FROM {}", schema.table)).execute(&self.pool).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures::{self, HOURLY};
+    use crate::models::series::SWAPS;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn failed_update_keeps_previous_dataset() {
+        let dir = TempDir::new().unwrap();
+        let sqlite = SqliteClient::new(&fixtures::config(&dir)).await.unwrap();
+        let data = fixtures::dataset(4, 0);
+        sqlite.update_rune_pool(HOURLY, &data).await.unwrap();
+
+        // The repeated start time breaks the primary key after the deletes and first inserts ran.
+        let mut duplicate = fixtures::dataset(6, 0);
+        duplicate.intervals[5] = duplicate.intervals[4].clone();
+        assert!(sqlite.update_rune_pool(HOURLY, &duplicate).await.is_err());
+        assert_eq!(sqlite.get_rune_pool(HOURLY).await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn values_past_i64_are_refused() {
+        let dir = TempDir::new().unwrap();
+        let sqlite = SqliteClient::new(&fixtures::config(&dir)).await.unwrap();
+        let signed = fixtures::extreme(i64::MAX as u64);
+        let records = fixtures::extreme_records(i64::MAX as u64);
+        sqlite.update_rune_pool(HOURLY, &signed).await.unwrap();
+        sqlite.update_series(&SWAPS, HOURLY, "", &records).await.unwrap();
+        assert_eq!(sqlite.get_rune_pool(HOURLY).await.unwrap(), signed);
+        assert_eq!(sqlite.get_series(&SWAPS, HOURLY, "").await.unwrap(), records);
+
+        // Past i64::MAX the write is refused and the store keeps what it had.
+        let error = sqlite.update_rune_pool(HOURLY, &fixtures::extreme(u64::MAX)).await.unwrap_err();
+        assert!(error.to_string().contains("end_units = 18446744073709551615"), "{}", error);
+        assert!(sqlite.update_series(&SWAPS, HOURLY, "", &fixtures::extreme_records(u64::MAX)).await.is_err());
+        assert_eq!(sqlite.get_rune_pool(HOURLY).await.unwrap(), signed);
+        assert_eq!(sqlite.get_series(&SWAPS, HOURLY, "").await.unwrap(), records);
+    }
+
+    #[tokio::test]
+    async fn rejects_history_tables_that_drifted() {
+        let dir = TempDir::new().unwrap();
+        let sqlite = SqliteClient::new(&fixtures::config(&dir)).await.unwrap();
+        sqlx::query("ALTER TABLE history_tvl DROP COLUMN rune_price_usd").execute(&sqlite.pool).await.unwrap();
+        let error = check_history_columns(&sqlite.pool).await.unwrap_err();
+        assert!(error.to_string().contains("history_tvl"), "{}", error);
+    }
+}
@@ -0,0 +1,23 @@
+use crate::db::{dataset, history};
+use crate::models::series;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Total size of the files under `path`, recursing into subdirectories.
+pub fn dir_size(path: &Path) -> Result<u64, Box<dyn Error>> {
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        total += if metadata.is_dir() { dir_size(&entry.path())? } else { metadata.len() };
+    }
+    Ok(total)
+}
+
+/// Key prefixes of every interval and history record in the stores that keep everything in
+/// one keyspace. Keys under them are the rows the footprint report divides by.
+pub fn row_prefixes() -> Vec<Vec<u8>> {
+    let tables = series::ALL.iter().map(|schema| history::table_prefix(schema.table));
+    std::iter::once(dataset::INTERVALS_PREFIX.to_vec()).chain(tables).collect()
+}
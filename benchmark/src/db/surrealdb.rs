@@ -1,49 +1,186 @@
 use crate::config::Config;
+use crate::db::numeric;
+use crate::error::NotFound;
+use crate::models::series::{self, FieldKind, Record, Schema, Value};
 use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
-use surrealdb::engine::remote::ws::{Ws, Client};
-use surrealdb::opt::auth::Root;
+use futures_util::future::try_join_all;
+use surrealdb::engine::any::{self, Any};
+use surrealdb::opt::auth::{Database, Namespace, Root};
 use surrealdb::Surreal;
 use std::error::Error;
 
+const SCHEMA: &str = "
+    DEFINE TABLE IF NOT EXISTS meta SCHEMAFULL;
+    DEFINE FIELD IF NOT EXISTS dataset ON meta TYPE string;
+    DEFINE FIELD IF NOT EXISTS start_time ON meta TYPE int;
+    DEFINE FIELD IF NOT EXISTS end_time ON meta TYPE int;
+    DEFINE FIELD IF NOT EXISTS start_count ON meta TYPE int;
+    DEFINE FIELD IF NOT EXISTS end_count ON meta TYPE int;
+    DEFINE FIELD IF NOT EXISTS start_units ON meta TYPE int;
+    DEFINE FIELD IF NOT EXISTS end_units ON meta TYPE int;
+    DEFINE INDEX IF NOT EXISTS meta_dataset ON meta FIELDS dataset UNIQUE;
+
+    DEFINE TABLE IF NOT EXISTS interval SCHEMAFULL;
+    DEFINE FIELD IF NOT EXISTS dataset ON interval TYPE string;
+    DEFINE FIELD IF NOT EXISTS start_time ON interval TYPE int;
+    DEFINE FIELD IF NOT EXISTS end_time ON interval TYPE int;
+    DEFINE FIELD IF NOT EXISTS count ON interval TYPE int;
+    DEFINE FIELD IF NOT EXISTS units ON interval TYPE int;
+    REMOVE INDEX IF EXISTS interval_start_time ON interval;
+    DEFINE INDEX IF NOT EXISTS interval_dataset_start_time ON interval FIELDS dataset, start_time UNIQUE;
+";
+
+/// Schemafull table for a series, with one field per schema field. Also drops the JSON
+/// `payload` field older versions stored the intervals in. Record ids `[dataset, scope,
+/// start_time]` keep records unique: a unique index over all three fields misses rows when
+/// queried on just `dataset` and `scope`.
+fn series_schema(schema: &Schema) -> String {
+    let table = schema.table;
+    let mut query = format!(
+        "DEFINE TABLE IF NOT EXISTS {table} SCHEMAFULL;
+         REMOVE FIELD IF EXISTS payload ON {table};
+         DEFINE FIELD IF NOT EXISTS dataset ON {table} TYPE string;
+         DEFINE FIELD IF NOT EXISTS scope ON {table} TYPE string;
+         DEFINE FIELD IF NOT EXISTS start_time ON {table} TYPE int;
+         DEFINE FIELD IF NOT EXISTS end_time ON {table} TYPE int;
+         REMOVE INDEX IF EXISTS {table}_scope_start_time ON {table};
+         DEFINE INDEX IF NOT EXISTS {table}_dataset_scope ON {table} FIELDS dataset, scope;"
+    );
+    for field in schema.fields {
+        let field_type = match field.kind {
+            FieldKind::U64 => "int",
+            FieldKind::F64 => "float",
+        };
+        query.push_str(&format!("DEFINE FIELD IF NOT EXISTS {} ON {} TYPE {};", field.name, table, field_type));
+    }
+    query
+}
+
+fn series_fields(schema: &Schema) -> String {
+    let mut fields = vec!["start_time", "end_time"];
+    fields.extend(schema.fields.iter().map(|field| field.name));
+    fields.join(", ")
+}
+
+/// One connection per namespace; a `Surreal` handle's namespace is shared by all its clones.
 pub struct SurrealDBClient {
-    db: Surreal<Client>,
+    namespaces: Vec<(String, Surreal<Any>)>,
+}
+
+/// Summed JSON length of each document, so no documents count as 0 bytes.
+fn json_size<T: serde::Serialize>(documents: &[T]) -> Result<u64, serde_json::Error> {
+    let mut total = 0;
+    for document in documents {
+        total += serde_json::to_vec(document)?.len() as u64;
+    }
+    Ok(total)
 }
 
 impl SurrealDBClient {
     pub async fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
-        let db = Surreal::new::<Ws>(&config.surrealdb_url).await?;
-        db.signin(Root {
-            username: "root",
-            password: "root",
-        })
-        .await?;
-        db.use_ns("runepool_ns").use_db("runepool_db").await?;
-        Ok(SurrealDBClient { db })
-    }
-
-    pub async fn update_rune_pool(&self, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
-        self.db
-            .query("CREATE meta SET start_time = $start_time, end_time = $end_time, start_count = $start_count, end_count = $end_count, start_units = $start_units, end_units = $end_units")
-            .bind(("start_time", response.meta.start_time))
-            .bind(("end_time", response.meta.end_time))
-            .bind(("start_count", response.meta.start_count))
-            .bind(("end_count", response.meta.end_count))
-            .bind(("start_units", response.meta.start_units))
-            .bind(("end_units", response.meta.end_units))
+        let endpoint = match config.surrealdb_engine.as_str() {
+            "ws" => format!("ws://{}", config.surrealdb_url),
+            "mem" => "mem://".to_string(),
+            "rocksdb" => format!("rocksdb://{}", config.surrealdb_path),
+            other => return Err(format!("Unknown SurrealDB engine: {}", other).into()),
+        };
+        if config.surrealdb_engine == "rocksdb" && config.surrealdb_namespaces.len() > 1 {
+            return Err("The embedded RocksDB engine can only be opened for a single namespace".into());
+        }
+
+        let mut namespaces = Vec::new();
+        for ns in &config.surrealdb_namespaces {
+            let db = any::connect(endpoint.as_str()).await?;
+            // Embedded engines run without authentication.
+            if config.surrealdb_engine == "ws" {
+                Self::signin(&db, config, ns).await?;
+            }
+            db.use_ns(ns.as_str()).use_db(config.surrealdb_database.as_str()).await?;
+            db.query(SCHEMA).await?.check()?;
+            db.query(format!("REMOVE TABLE IF EXISTS {}", series::RETIRED_TABLE)).await?.check()?;
+            for schema in series::ALL {
+                db.query(series_schema(schema)).await?.check()?;
+            }
+            namespaces.push((ns.clone(), db));
+        }
+        if namespaces.is_empty() {
+            return Err("No SurrealDB namespace configured".into());
+        }
+
+        Ok(SurrealDBClient { namespaces })
+    }
+
+    async fn signin(db: &Surreal<Any>, config: &Config, ns: &str) -> Result<(), Box<dyn Error>> {
+        let username = config.surrealdb_user.as_str();
+        let password = config.surrealdb_pass.as_str();
+        match config.surrealdb_auth_level.as_str() {
+            "root" => {
+                db.signin(Root { username, password }).await?;
+            }
+            "namespace" => {
+                db.signin(Namespace { namespace: ns, username, password }).await?;
+            }
+            "database" => {
+                db.signin(Database {
+                    namespace: ns,
+                    database: config.surrealdb_database.as_str(),
+                    username,
+                    password,
+                })
+                .await?;
+            }
+            other => return Err(format!("Unknown SurrealDB auth level: {}", other).into()),
+        }
+        Ok(())
+    }
+
+    fn namespace(&self, ns: Option<&str>) -> Result<&Surreal<Any>, Box<dyn Error>> {
+        match ns {
+            None => Ok(&self.namespaces[0].1),
+            Some(ns) => self
+                .namespaces
+                .iter()
+                .find(|(name, _)| name == ns)
+                .map(|(_, db)| db)
+                .ok_or_else(|| format!("Unknown SurrealDB namespace: {}", ns).into()),
+        }
+    }
+
+    /// Writes the dataset into every configured namespace concurrently. SurrealDB's `int`
+    /// is signed 64-bit and larger binds silently lose precision, so values are checked first.
+    pub async fn update_rune_pool(&self, dataset: &str, response: &DbRunePoolResponse) -> Result<(), Box<dyn Error>> {
+        let meta = numeric::meta_row(&response.meta)?;
+        let intervals = response.intervals.iter().map(numeric::interval_row).collect::<Result<Vec<_>, _>>()?;
+        try_join_all(self.namespaces.iter().map(|(_, db)| Self::write(db, dataset, meta, &intervals))).await?;
+        Ok(())
+    }
+
+    async fn write(db: &Surreal<Any>, dataset: &str, meta: [i64; 6], intervals: &[[i64; 4]]) -> surrealdb::Result<()> {
+        db.query("DELETE meta WHERE dataset = $dataset; DELETE interval WHERE dataset = $dataset;")
+            .bind(("dataset", dataset.to_string()))
+            .await?
+            .check()?;
+
+        db
+            .query("CREATE meta SET dataset = $dataset, start_time = $start_time, end_time = $end_time, start_count = $start_count, end_count = $end_count, start_units = $start_units, end_units = $end_units")
+            .bind(("dataset", dataset.to_string()))
+            .bind(("start_time", meta[0]))
+            .bind(("end_time", meta[1]))
+            .bind(("start_count", meta[2]))
+            .bind(("end_count", meta[3]))
+            .bind(("start_units", meta[4]))
+            .bind(("end_units", meta[5]))
             .await?
             .check()?;
 
-        for interval in &response.intervals {
-            let query = format!(
-                "CREATE interval:{} SET start_time = $start_time, end_time = $end_time, count = $count, units = $units",
-                interval.start_time
-            );
-            self.db
-                .query(&query)
-                .bind(("start_time", interval.start_time))
-                .bind(("end_time", interval.end_time))
-                .bind(("count", interval.count))
-                .bind(("units", interval.units))
+        for interval in intervals {
+            db
+                .query("CREATE type::thing('interval', [$dataset, $start_time]) SET dataset = $dataset, start_time = $start_time, end_time = $end_time, count = $count, units = $units")
+                .bind(("dataset", dataset.to_string()))
+                .bind(("start_time", interval[0]))
+                .bind(("end_time", interval[1]))
+                .bind(("count", interval[2]))
+                .bind(("units", interval[3]))
                 .await?
                 .check()?;
         }
@@ -51,24 +188,233 @@ impl SurrealDBClient {
         Ok(())
     }
 
-    pub async fn get_rune_pool(&self) -> Result<DbRunePoolResponse, Box<dyn Error>> {
-        let metas: Vec<DbMeta> = self.db
-            .query("SELECT start_time, end_time, start_count, end_count, start_units, end_units FROM meta")
+    /// Reads from `ns`, or from the first configured namespace when `None`.
+    pub async fn get_rune_pool(&self, dataset: &str, ns: Option<&str>) -> Result<DbRunePoolResponse, Box<dyn Error>> {
+        let db = self.namespace(ns)?;
+        let metas: Vec<DbMeta> = db
+            .query("SELECT start_time, end_time, start_count, end_count, start_units, end_units FROM meta WHERE dataset = $dataset")
+            .bind(("dataset", dataset.to_string()))
             .await?
             .take(0)?;
-        let meta = metas.into_iter().next().ok_or("Meta not found")?;
+        // The unique index rules this out for new rows; older stores may still hold duplicates.
+        if metas.len() > 1 {
+            return Err(format!("{} meta rows for dataset {}", metas.len(), dataset).into());
+        }
+        let meta = metas.into_iter().next().ok_or(NotFound)?;
 
-        let intervals: Vec<DbInterval> = self.db
-            .query("SELECT start_time, end_time, count, units FROM interval ORDER BY start_time ASC")
+        let intervals: Vec<DbInterval> = db
+            .query("SELECT start_time, end_time, count, units FROM interval WHERE dataset = $dataset ORDER BY start_time ASC")
+            .bind(("dataset", dataset.to_string()))
             .await?
             .take(0)?;
 
         Ok(DbRunePoolResponse { meta, intervals })
     }
 
+    /// Replaces one series in every configured namespace.
+    pub async fn update_series(
+        &self,
+        schema: &Schema,
+        dataset: &str,
+        scope: &str,
+        records: &[Record],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut documents = Vec::with_capacity(records.len());
+        for record in records {
+            let mut document = serde_json::Map::new();
+            document.insert("dataset".to_string(), dataset.into());
+            document.insert("scope".to_string(), scope.into());
+            document.insert("start_time".to_string(), numeric::to_i64("start_time", record.start_time)?.into());
+            document.insert("end_time".to_string(), numeric::to_i64("end_time", record.end_time)?.into());
+            for (field, value) in schema.fields.iter().zip(&record.values) {
+                let value = match *value {
+                    Value::U64(n) => numeric::to_i64(field.name, n)?.into(),
+                    Value::F64(x) => x.into(),
+                };
+                document.insert(field.name.to_string(), value);
+            }
+            documents.push(serde_json::Value::Object(document));
+        }
+        try_join_all(self.namespaces.iter().map(|(_, db)| Self::write_series(db, schema.table, dataset, scope, &documents))).await?;
+        Ok(())
+    }
+
+    async fn write_series(
+        db: &Surreal<Any>,
+        table: &str,
+        dataset: &str,
+        scope: &str,
+        documents: &[serde_json::Value],
+    ) -> surrealdb::Result<()> {
+        let query = format!(
+            "DELETE {table} WHERE dataset = $dataset AND scope = $scope;
+             FOR $record IN $records {{
+                 CREATE type::thing('{table}', [$dataset, $scope, $record.start_time]) CONTENT $record;
+             }};",
+            table = table
+        );
+        db.query(&query)
+            .bind(("dataset", dataset.to_string()))
+            .bind(("scope", scope.to_string()))
+            .bind(("records", documents.to_vec()))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    pub async fn get_series(
+        &self,
+        schema: &Schema,
+        dataset: &str,
+        scope: &str,
+        ns: Option<&str>,
+    ) -> Result<Vec<Record>, Box<dyn Error>> {
+        let db = self.namespace(ns)?;
+        let query = format!(
+            "SELECT {} FROM {} WHERE dataset = $dataset AND scope = $scope ORDER BY start_time ASC",
+            series_fields(schema),
+            schema.table
+        );
+        let documents: Vec<serde_json::Value> = db
+            .query(&query)
+            .bind(("dataset", dataset.to_string()))
+            .bind(("scope", scope.to_string()))
+            .await?
+            .take(0)?;
+        if documents.is_empty() {
+            return Err(NotFound.into());
+        }
+        documents.iter().map(|document| Self::parse_record(schema, document)).collect()
+    }
+
+    fn parse_record(schema: &Schema, document: &serde_json::Value) -> Result<Record, Box<dyn Error>> {
+        let field = |name: &str| document.get(name).ok_or_else(|| format!("Stored {} record has no {}", schema.name, name));
+        let uint = |name: &str| field(name)?.as_u64().ok_or_else(|| format!("Stored {} field {} is not an integer", schema.name, name));
+        let mut values = Vec::with_capacity(schema.fields.len());
+        for f in schema.fields {
+            values.push(match f.kind {
+                FieldKind::U64 => Value::U64(uint(f.name)?),
+                FieldKind::F64 => Value::F64(
+                    field(f.name)?.as_f64().ok_or_else(|| format!("Stored {} field {} is not a number", schema.name, f.name))?,
+                ),
+            });
+        }
+        Ok(Record { start_time: uint("start_time")?, end_time: uint("end_time")?, values })
+    }
+
+    /// An estimate: SurrealDB doesn't expose storage size (`INFO FOR TABLE` only lists the
+    /// schema), so this is the JSON size of the stored records, history included, across all
+    /// namespaces. Queries the tables directly, so an empty store reports 0 rather than NotFound.
+    pub async fn storage_size(&self) -> Result<u64, Box<dyn Error>> {
+        let mut total = 0;
+        for (_, db) in &self.namespaces {
+            let metas: Vec<DbMeta> = db
+                .query("SELECT start_time, end_time, start_count, end_count, start_units, end_units FROM meta")
+                .await?
+                .take(0)?;
+            let intervals: Vec<DbInterval> =
+                db.query("SELECT start_time, end_time, count, units FROM interval").await?.take(0)?;
+            total += json_size(&metas)? + json_size(&intervals)?;
+            for schema in series::ALL {
+                let documents: Vec<serde_json::Value> =
+                    db.query(format!("SELECT {} FROM {}", series_fields(schema), schema.table)).await?.take(0)?;
+                total += json_size(&documents)?;
+            }
+        }
+        Ok(total)
+    }
+
+    /// Interval and history records across every dataset and namespace.
+    pub async fn row_count(&self) -> Result<u64, Box<dyn Error>> {
+        let mut rows = 0;
+        for (_, db) in &self.namespaces {
+            for table in std::iter::once("interval").chain(series::ALL.iter().map(|schema| schema.table)) {
+                let count: Option<u64> =
+                    db.query(format!("SELECT count() FROM {} GROUP ALL", table)).await?.take((0, "count"))?;
+                rows += count.unwrap_or(0);
+            }
+        }
+        Ok(rows)
+    }
+
+    pub async fn clear_dataset(&self, dataset: &str) -> Result<(), Box<dyn Error>> {
+        try_join_all(self.namespaces.iter().map(|(_, db)| Self::clear_namespace_dataset(db, dataset))).await?;
+        Ok(())
+    }
+
+    async fn clear_namespace_dataset(db: &Surreal<Any>, dataset: &str) -> surrealdb::Result<()> {
+        let tables = ["meta", "interval"].into_iter().chain(series::ALL.iter().map(|schema| schema.table));
+        for table in tables {
+            db.query(format!("DELETE {} WHERE dataset = $dataset", table))
+                .bind(("dataset", dataset.to_string()))
+                .await?
+                .check()?;
+        }
+        Ok(())
+    }
+
     pub async fn clear(&self) -> Result<(), Box<dyn Error>> {
-        self.db.query("DELETE meta").await?.check()?;
-        self.db.query("DELETE interval").await?.check()?;
+        try_join_all(self.namespaces.iter().map(|(_, db)| Self::clear_namespace(db))).await?;
+        Ok(())
+    }
+
+    async fn clear_namespace(db: &Surreal<Any>) -> surrealdb::Result<()> {
+        db.query("DELETE meta").await?.check()?;
+        db.query("DELETE interval").await?.check()?;
+        for schema in series::ALL {
+            db.query(format!("DELETE {}", schema.table)).await?.check()?;
+        }
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fixtures::{self, HOURLY};
+    use crate::models::series::SWAPS;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn values_past_i64_are_refused() {
+        let dir = TempDir::new().unwrap();
+        let surrealdb = SurrealDBClient::new(&fixtures::config(&dir)).await.unwrap();
+        let signed = fixtures::extreme(i64::MAX as u64);
+        let records = fixtures::extreme_records(i64::MAX as u64);
+        surrealdb.update_rune_pool(HOURLY, &signed).await.unwrap();
+        surrealdb.update_series(&SWAPS, HOURLY, "", &records).await.unwrap();
+        assert_eq!(surrealdb.get_rune_pool(HOURLY, None).await.unwrap(), signed);
+        assert_eq!(surrealdb.get_series(&SWAPS, HOURLY, "", None).await.unwrap(), records);
+
+        // Past i64::MAX the write is refused and the store keeps what it had.
+        assert!(surrealdb.update_rune_pool(HOURLY, &fixtures::extreme(u64::MAX)).await.is_err());
+        assert!(surrealdb.update_series(&SWAPS, HOURLY, "", &fixtures::extreme_records(u64::MAX)).await.is_err());
+        assert_eq!(surrealdb.get_rune_pool(HOURLY, None).await.unwrap(), signed);
+        assert_eq!(surrealdb.get_series(&SWAPS, HOURLY, "", None).await.unwrap(), records);
+    }
+
+    #[tokio::test]
+    async fn storage_size_counts_from_an_empty_store() {
+        let dir = TempDir::new().unwrap();
+        let surrealdb = SurrealDBClient::new(&fixtures::config(&dir)).await.unwrap();
+        assert_eq!(surrealdb.storage_size().await.unwrap(), 0);
+        surrealdb.update_rune_pool(HOURLY, &fixtures::dataset(3, 0)).await.unwrap();
+        assert!(surrealdb.storage_size().await.unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn meta_is_unique_per_dataset() {
+        let dir = TempDir::new().unwrap();
+        let surrealdb = SurrealDBClient::new(&fixtures::config(&dir)).await.unwrap();
+        surrealdb.update_rune_pool(HOURLY, &fixtures::dataset(3, 0)).await.unwrap();
+        surrealdb.update_rune_pool(HOURLY, &fixtures::dataset(2, 0)).await.unwrap();
+        let (_, db) = &surrealdb.namespaces[0];
+        let duplicate = db
+            .query("CREATE meta CONTENT { dataset: $dataset, start_time: 0, end_time: 0, start_count: 0, end_count: 0, start_units: 0, end_units: 0 }")
+            .bind(("dataset", HOURLY))
+            .await
+            .unwrap()
+            .check();
+        assert!(duplicate.is_err());
+        assert_eq!(surrealdb.get_rune_pool(HOURLY, None).await.unwrap(), fixtures::dataset(2, 0));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::models::validate::Violation;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use std::error::Error;
+use std::fmt;
+
+/// Returned by clients when the backend holds no dataset.
+#[derive(Debug)]
+pub struct NotFound;
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Meta not found")
+    }
+}
+
+impl Error for NotFound {}
+
+/// Every failure the API reports, one variant per class of cause.
+#[derive(Debug)]
+pub enum AppError {
+    /// 404: the backend is reachable but stores nothing.
+    NotFound { backend: String },
+    /// 400: missing or malformed query parameters.
+    BadRequest(String),
+    /// 422: a payload failed validation.
+    Invalid { source: String, violations: Vec<Violation> },
+    /// 502: Midgard failed or returned something unparseable.
+    Upstream(String),
+    /// 503: the backend could not be reached.
+    Unavailable { backend: String, message: String },
+    /// 500: anything else a backend reported.
+    Backend { backend: String, message: String },
+}
+
+/// Connection-level failures from the client libraries, and I/O failures from the embedded
+/// engines, which map to 503.
+fn is_unavailable(error: &(dyn Error + 'static)) -> bool {
+    if let Some(e) = error.downcast_ref::<std::io::Error>() {
+        use std::io::ErrorKind::*;
+        return matches!(e.kind(), ConnectionRefused | ConnectionReset | ConnectionAborted | NotConnected | TimedOut);
+    }
+    if let Some(e) = error.downcast_ref::<sqlx::Error>() {
+        return matches!(e, sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed);
+    }
+    if let Some(e) = error.downcast_ref::<mongodb::error::Error>() {
+        use mongodb::error::ErrorKind;
+        return matches!(*e.kind, ErrorKind::ServerSelection { .. } | ErrorKind::Io(_) | ErrorKind::ConnectionPoolCleared { .. });
+    }
+    if let Some(e) = error.downcast_ref::<redis::RedisError>() {
+        return e.is_connection_refusal() || e.is_connection_dropped() || e.is_timeout() || e.is_io_error();
+    }
+    if let Some(surrealdb::Error::Api(e)) = error.downcast_ref::<surrealdb::Error>() {
+        use surrealdb::error::Api;
+        return matches!(e, Api::Http(_) | Api::Ws(_) | Api::ConnectionUninitialised);
+    }
+    if let Some(e) = error.downcast_ref::<rocksdb::Error>() {
+        use rocksdb::ErrorKind;
+        return matches!(e.kind(), ErrorKind::IOError | ErrorKind::TimedOut | ErrorKind::Busy | ErrorKind::TryAgain);
+    }
+    // libduckdb-sys reports every failure with the same code; the message carries DuckDB's
+    // exception type.
+    if let Some(duckdb::Error::DuckDBFailure(_, Some(message))) = error.downcast_ref::<duckdb::Error>() {
+        return message.starts_with("IO Error");
+    }
+    false
+}
+
+impl AppError {
+    /// Classifies an error returned by the named backend's client.
+    pub fn backend(backend: &str, error: Box<dyn Error>) -> Self {
+        let backend = backend.to_string();
+        let not_found = error.is::<NotFound>()
+            || matches!(error.downcast_ref::<sqlx::Error>(), Some(sqlx::Error::RowNotFound));
+        if not_found {
+            AppError::NotFound { backend }
+        } else if is_unavailable(error.as_ref()) {
+            AppError::Unavailable { backend, message: error.to_string() }
+        } else {
+            AppError::Backend { backend, message: error.to_string() }
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            AppError::NotFound { .. } => StatusCode::NOT_FOUND,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Invalid { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            AppError::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Backend { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound { backend } => write!(f, "{}: no data stored", backend),
+            AppError::BadRequest(message) => write!(f, "{}", message),
+            AppError::Invalid { source, violations } => {
+                write!(f, "Invalid {} payload ({} violations)", source, violations.len())
+            }
+            AppError::Upstream(message) => write!(f, "{}", message),
+            AppError::Unavailable { backend, message } | AppError::Backend { backend, message } => {
+                write!(f, "{}: {}", backend, message)
+            }
+        }
+    }
+}
+
+impl Error for AppError {}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let body = match &self {
+            AppError::NotFound { backend } => {
+                json!({ "error": "not_found", "backend": backend, "message": "Meta not found" })
+            }
+            AppError::BadRequest(message) => json!({ "error": "bad_request", "message": message }),
+            AppError::Invalid { source, violations } => json!({
+                "error": "invalid_payload",
+                "message": format!("Invalid {} payload", source),
+                "violations": violations
+            }),
+            AppError::Upstream(message) => json!({ "error": "upstream", "backend": "midgard", "message": message }),
+            AppError::Unavailable { backend, message } => {
+                json!({ "error": "unavailable", "backend": backend, "message": message })
+            }
+            AppError::Backend { backend, message } => {
+                json!({ "error": "backend", "backend": backend, "message": message })
+            }
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_backend_errors() {
+        let status = |error: Box<dyn Error>| AppError::backend("test", error).status();
+
+        assert_eq!(status(NotFound.into()), StatusCode::NOT_FOUND);
+        assert_eq!(status(sqlx::Error::RowNotFound.into()), StatusCode::NOT_FOUND);
+        assert_eq!(
+            status(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into()),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(status(sqlx::Error::PoolTimedOut.into()), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status("Corrupt flat file meta".into()), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let surreal = |e: surrealdb::error::Api| -> Box<dyn Error> { Box::new(surrealdb::Error::Api(e)) };
+        assert_eq!(status(surreal(surrealdb::error::Api::Ws("closed".into()))), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(surreal(surrealdb::error::Api::Http("refused".into()))), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(surreal(surrealdb::error::Api::ConnectionUninitialised)), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(surreal(surrealdb::error::Api::Query("bad".into()))), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let duckdb = |message: &str| -> Box<dyn Error> {
+            Box::new(duckdb::Error::DuckDBFailure(duckdb::ffi::Error::new(1), Some(message.to_string())))
+        };
+        assert_eq!(status(duckdb("IO Error: Could not set lock on file")), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(duckdb("Binder Error: no such column")), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -1,22 +1,57 @@
 mod config;
+mod error;
 mod models;
 mod db;
+mod midgard;
 mod api;
 
-use api::handlers::{clear_databases, fetch_and_update_rune_pool, get_rune_pool, update_rune_pool, AppState};
+use api::handlers::{
+    clear_databases, export_parquet, fetch_and_update_rune_pool, fetch_history, get_history, get_latest,
+    get_range, get_rune_pool, get_stats, import_parquet, resample_rune_pool, update_rune_pool, verify,
+    verify_backends, AppState,
+};
+use std::collections::HashMap;
 use axum::{routing::get, routing::post, routing::delete, Router};
 use config::Config;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = Config::load();
+    let args: Vec<String> = std::env::args().collect();
+
+    // `benchmark mock-midgard` serves fake RUNEPool history; point API_URL at it to run offline.
+    if args.get(1).map(String::as_str) == Some("mock-midgard") {
+        return midgard::mock::serve(&config).await;
+    }
+
     let state = AppState::new(config.clone()).await?;
+
+    // `benchmark verify [reference]` runs the consistency check once and exits. The memory
+    // store starts empty in a fresh process, so the reference defaults to Postgres here.
+    if args.get(1).map(String::as_str) == Some("verify") {
+        let reference = args.get(2).map(String::as_str).unwrap_or("psql");
+        let (consistent, report) = verify_backends(&state, reference, &HashMap::new()).await?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        if !consistent {
+            return Err("Backends are inconsistent".into());
+        }
+        return Ok(());
+    }
     
     let app = Router::new()
     .route("/update", post(update_rune_pool))
     .route("/get", get(get_rune_pool))
+    .route("/range", get(get_range))
+    .route("/latest", get(get_latest))
+    .route("/resample", get(resample_rune_pool))
+    .route("/stats", get(get_stats))
+    .route("/verify", get(verify))
     .route("/fetch-and-update", post(fetch_and_update_rune_pool))
+    .route("/history", get(get_history))
+    .route("/history/fetch", post(fetch_history))
     .route("/clear", delete(clear_databases))
+    .route("/parquet/export", post(export_parquet))
+    .route("/parquet/import", post(import_parquet))
     .with_state(state);
 
     let config = Config::load();
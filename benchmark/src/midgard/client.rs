@@ -0,0 +1,286 @@
+use crate::config::Config;
+use crate::models::rune_pool::ApiRunePoolResponse;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Upper bound on a single backoff, including server-supplied `Retry-After`.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Where Midgard responses come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchMode {
+    /// Straight from the network.
+    Live,
+    /// From the network, saving every successful response to the fixtures directory.
+    Record,
+    /// From the fixtures directory only; a request with no recording is an error.
+    Replay,
+}
+
+impl FetchMode {
+    pub fn parse(value: &str) -> Result<Self, Box<dyn Error>> {
+        match value {
+            "live" => Ok(FetchMode::Live),
+            "record" => Ok(FetchMode::Record),
+            "replay" => Ok(FetchMode::Replay),
+            other => Err(format!("Unknown Midgard mode: {}", other).into()),
+        }
+    }
+}
+
+/// A raw Midgard response together with the request that produced it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Recording {
+    pub url: String,
+    pub params: BTreeMap<String, String>,
+    pub body: String,
+}
+
+/// How a fetch went, returned alongside the data.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FetchReport {
+    pub mode: &'static str,
+    /// Requests sent over the network; zero when replaying.
+    pub attempts: u32,
+    /// Why each retried attempt failed.
+    pub failures: Vec<String>,
+    pub backoff_ms: u128,
+    /// Time spent waiting on the client-side rate limiter.
+    pub throttled_ms: u128,
+}
+
+/// Spaces requests at least `interval` apart across all callers.
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    async fn acquire(&self) -> Duration {
+        let wait = {
+            let mut next = self.next.lock().await;
+            let now = Instant::now();
+            let wait = next.saturating_duration_since(now);
+            *next = (*next).max(now) + self.interval;
+            wait
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        wait
+    }
+}
+
+pub struct MidgardClient {
+    http: reqwest::Client,
+    mode: FetchMode,
+    fixtures_dir: PathBuf,
+    max_retries: u32,
+    backoff: Duration,
+    limiter: Option<RateLimiter>,
+}
+
+/// File name for a request: the URL path and the sorted query parameters, made filename-safe.
+fn fixture_name(url: &Url, params: &BTreeMap<String, String>) -> String {
+    let query: Vec<String> = params.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    let raw = format!("{}_{}", url.path().trim_matches('/'), query.join("_"));
+    let safe: String = raw
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "=-_.".contains(c) { c } else { '_' })
+        .collect();
+    format!("{}.json", safe)
+}
+
+impl MidgardClient {
+    pub fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.midgard_timeout_ms))
+            .user_agent(&config.midgard_user_agent)
+            .build()?;
+        let limiter = (config.midgard_rate_limit > 0).then(|| RateLimiter {
+            interval: Duration::from_secs(1) / config.midgard_rate_limit,
+            next: Mutex::new(Instant::now()),
+        });
+        Ok(MidgardClient {
+            http,
+            mode: FetchMode::parse(&config.midgard_mode)?,
+            fixtures_dir: PathBuf::from(&config.midgard_fixtures_dir),
+            max_retries: config.midgard_max_retries,
+            backoff: Duration::from_millis(config.midgard_backoff_ms),
+            limiter,
+        })
+    }
+
+    /// Sends the request, retrying timeouts, connection failures, 429 and 5xx with
+    /// exponential backoff. Other statuses fail immediately.
+    async fn send(&self, url: &Url, params: &BTreeMap<String, String>, report: &mut FetchReport) -> Result<String, String> {
+        loop {
+            if let Some(limiter) = &self.limiter {
+                report.throttled_ms += limiter.acquire().await.as_millis();
+            }
+            report.attempts += 1;
+
+            let (failure, retry_after) = match self.http.get(url.clone()).query(params).send().await {
+                Ok(response) if response.status().is_success() => {
+                    return response.text().await.map_err(|e| e.to_string());
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+                        return Err(format!("Midgard returned {}", status));
+                    }
+                    let retry_after = response
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    (format!("Midgard returned {}", status), retry_after)
+                }
+                Err(e) if e.is_timeout() || e.is_connect() => (e.to_string(), None),
+                Err(e) => return Err(e.to_string()),
+            };
+
+            report.failures.push(failure.clone());
+            if report.attempts > self.max_retries {
+                return Err(format!("giving up after {} attempts: {}", report.attempts, failure));
+            }
+            let backoff = retry_after
+                .unwrap_or_else(|| self.backoff.saturating_mul(1 << (report.attempts - 1).min(16)))
+                .min(MAX_BACKOFF);
+            report.backoff_ms += backoff.as_millis();
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
+    /// Raw response body for `url` with `params`, honoring the fetch mode.
+    pub async fn get_raw(&self, url: &str, params: &[(&str, String)]) -> Result<(String, FetchReport), Box<dyn Error>> {
+        let url = Url::parse(url)?;
+        let params: BTreeMap<String, String> = params.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+        let path = self.fixtures_dir.join(fixture_name(&url, &params));
+        let mut report = FetchReport::default();
+
+        if self.mode == FetchMode::Replay {
+            report.mode = "replay";
+            let bytes = fs::read(&path)
+                .map_err(|e| format!("No recording at {} ({}); record it first with MIDGARD_MODE=record", path.display(), e))?;
+            let recording: Recording = serde_json::from_slice(&bytes)?;
+            return Ok((recording.body, report));
+        }
+
+        report.mode = if self.mode == FetchMode::Record { "record" } else { "live" };
+        let body = self.send(&url, &params, &mut report).await?;
+
+        if self.mode == FetchMode::Record {
+            fs::create_dir_all(&self.fixtures_dir)?;
+            let recording = Recording { url: url.to_string(), params, body };
+            fs::write(&path, serde_json::to_vec_pretty(&recording)?)?;
+            return Ok((recording.body, report));
+        }
+        Ok((body, report))
+    }
+
+    pub async fn runepool_history(
+        &self,
+        url: &str,
+        params: &[(&str, String)],
+    ) -> Result<(ApiRunePoolResponse, FetchReport), Box<dyn Error>> {
+        let (body, report) = self.get_raw(url, params).await?;
+        Ok((serde_json::from_str(&body)?, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midgard::mock::{self, ErrorMode, MockOptions};
+    use tempfile::TempDir;
+
+    fn test_config(mode: &str, dir: &TempDir) -> Config {
+        let mut config = Config::load();
+        config.midgard_mode = mode.to_string();
+        config.midgard_fixtures_dir = dir.path().to_string_lossy().into_owned();
+        config.midgard_timeout_ms = 200;
+        config.midgard_max_retries = 2;
+        config.midgard_backoff_ms = 10;
+        config.midgard_rate_limit = 0;
+        config
+    }
+
+    fn client(mode: &str, dir: &TempDir) -> MidgardClient {
+        MidgardClient::new(&test_config(mode, dir)).unwrap()
+    }
+
+    fn hourly(count: u64) -> [(&'static str, String); 3] {
+        [("interval", "hour".to_string()), ("from", "1700000000".to_string()), ("count", count.to_string())]
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_responses() {
+        let dir = TempDir::new().unwrap();
+        let url = mock::spawn(MockOptions::default()).await;
+        let params = hourly(12);
+
+        let (recorded, _) = client("record", &dir).get_raw(&url, &params).await.unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // Fixtures are keyed by path and params only, and nothing listens on port 9,
+        // so this can only succeed from disk.
+        let offline = "http://127.0.0.1:9/v2/history/runepool";
+        let replay = client("replay", &dir);
+        let (replayed, report) = replay.get_raw(offline, &params).await.unwrap();
+        assert_eq!(replayed, recorded);
+        assert_eq!(report.attempts, 0);
+
+        let other = [("interval", "day".to_string()), ("count", "3".to_string())];
+        assert!(replay.get_raw(offline, &other).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_with_backoff() {
+        let dir = TempDir::new().unwrap();
+        let midgard = client("live", &dir);
+
+        // Every second request fails: the first fetch succeeds outright, the second needs one retry.
+        let flaky = mock::spawn(MockOptions { error_mode: ErrorMode::Flaky(2), ..Default::default() }).await;
+        assert_eq!(midgard.get_raw(&flaky, &hourly(1)).await.unwrap().1.attempts, 1);
+        let (_, report) = midgard.get_raw(&flaky, &hourly(1)).await.unwrap();
+        assert_eq!(report.attempts, 2);
+        assert_eq!(report.failures, vec!["Midgard returned 503 Service Unavailable"]);
+        assert_eq!(report.backoff_ms, 10);
+
+        let down = mock::spawn(MockOptions { error_mode: ErrorMode::Status(500), ..Default::default() }).await;
+        let error = midgard.get_raw(&down, &hourly(1)).await.unwrap_err().to_string();
+        assert!(error.starts_with("giving up after 3 attempts"), "{}", error);
+
+        let missing = mock::spawn(MockOptions { error_mode: ErrorMode::Status(404), ..Default::default() }).await;
+        assert_eq!(midgard.get_raw(&missing, &hourly(1)).await.unwrap_err().to_string(), "Midgard returned 404 Not Found");
+
+        let slow = mock::spawn(MockOptions { latency: Duration::from_millis(500), ..Default::default() }).await;
+        assert!(midgard.get_raw(&slow, &hourly(1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rate_limits_requests() {
+        let dir = TempDir::new().unwrap();
+        let mut config = test_config("live", &dir);
+        config.midgard_rate_limit = 10;
+        let midgard = MidgardClient::new(&config).unwrap();
+        let url = mock::spawn(MockOptions::default()).await;
+
+        let start = Instant::now();
+        let mut throttled = 0;
+        for _ in 0..4 {
+            throttled += midgard.get_raw(&url, &hourly(1)).await.unwrap().1.throttled_ms;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert!(throttled > 0);
+    }
+}
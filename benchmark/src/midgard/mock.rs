@@ -0,0 +1,324 @@
+use crate::config::Config;
+use crate::models::rune_pool::{ApiInterval, ApiMeta, ApiRunePoolResponse};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Midgard rejects requests for more than 400 intervals.
+const MAX_COUNT: u64 = 400;
+
+/// How the mock misbehaves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorMode {
+    None,
+    /// Every request fails with this status.
+    Status(u16),
+    /// Every n-th request fails with 503.
+    Flaky(u64),
+    /// 200 with a truncated JSON body.
+    Malformed,
+    /// 200 with a well-formed payload that fails validation.
+    Inconsistent,
+}
+
+impl ErrorMode {
+    /// Parses `none`, `status:<code>`, `flaky:<n>`, `malformed` or `inconsistent`.
+    pub fn parse(value: &str) -> Result<Self, Box<dyn Error>> {
+        let (mode, arg) = value.split_once(':').unwrap_or((value, ""));
+        match mode {
+            "none" => Ok(ErrorMode::None),
+            "status" => Ok(ErrorMode::Status(arg.parse()?)),
+            "flaky" => match arg.parse()? {
+                0 => Err("flaky error mode needs n > 0".into()),
+                n => Ok(ErrorMode::Flaky(n)),
+            },
+            "malformed" => Ok(ErrorMode::Malformed),
+            "inconsistent" => Ok(ErrorMode::Inconsistent),
+            other => Err(format!("Unknown mock Midgard error mode: {}", other).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockOptions {
+    pub latency: Duration,
+    pub error_mode: ErrorMode,
+    /// Served instead of generated data when set; intervals are filtered, not resampled.
+    pub fixture: Option<ApiRunePoolResponse>,
+}
+
+impl MockOptions {
+    pub fn from_config(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let fixture = if config.mock_midgard_fixture.is_empty() {
+            None
+        } else {
+            let bytes = std::fs::read(&config.mock_midgard_fixture)?;
+            Some(serde_json::from_slice(&bytes)?)
+        };
+        Ok(MockOptions {
+            latency: Duration::from_millis(config.mock_midgard_latency_ms),
+            error_mode: ErrorMode::parse(&config.mock_midgard_error_mode)?,
+            fixture,
+        })
+    }
+}
+
+impl Default for MockOptions {
+    fn default() -> Self {
+        MockOptions { latency: Duration::ZERO, error_mode: ErrorMode::None, fixture: None }
+    }
+}
+
+struct MockState {
+    options: MockOptions,
+    requests: AtomicU64,
+}
+
+/// Seconds per Midgard interval name. Months, quarters and years use fixed 30/91/365-day lengths.
+fn interval_seconds(name: &str) -> Option<u64> {
+    match name {
+        "5min" => Some(300),
+        "hour" => Some(3_600),
+        "day" => Some(86_400),
+        "week" => Some(604_800),
+        "month" => Some(30 * 86_400),
+        "quarter" => Some(91 * 86_400),
+        "year" => Some(365 * 86_400),
+        _ => None,
+    }
+}
+
+// Deterministic member count and units at time `t`, so repeated runs see identical data.
+fn count_at(t: u64) -> u64 {
+    1_000 + (t / 3_600) % 500
+}
+
+fn units_at(t: u64) -> u64 {
+    1_000_000_000 + (t / 60) * 7
+}
+
+/// Start of the first bucket and the number of buckets selected by `from`/`to`/`count`,
+/// following Midgard's rules for combining them.
+fn select_buckets(params: &HashMap<String, String>, step: u64, now: u64) -> Result<(u64, u64), String> {
+    let number = |name: &str| -> Result<Option<u64>, String> {
+        params
+            .get(name)
+            .map(|v| v.parse::<u64>().map_err(|_| format!("'{}' must be an unsigned integer", name)))
+            .transpose()
+    };
+    let (from, to, count) = (number("from")?, number("to")?, number("count")?);
+    let floor = |t: u64| t / step * step;
+    let ceil = |t: u64| t.div_ceil(step).checked_mul(step).ok_or_else(|| format!("{} is out of range", t));
+    // Checked before any arithmetic so `count * step` can't overflow.
+    let checked_count = |count: u64| {
+        if count == 0 || count > MAX_COUNT {
+            return Err(format!("count must be between 1 and {}", MAX_COUNT));
+        }
+        Ok(count)
+    };
+
+    let (first, count) = match (from, to, count) {
+        (Some(_), Some(_), Some(_)) => return Err("count cannot be combined with both from and to".to_string()),
+        (Some(from), Some(to), None) => {
+            if to <= from {
+                return Err("'to' must be after 'from'".to_string());
+            }
+            (floor(from), checked_count((ceil(to)? - floor(from)) / step)?)
+        }
+        (Some(from), None, count) => (floor(from), checked_count(count.unwrap_or(MAX_COUNT))?),
+        (None, to, count) => {
+            let count = checked_count(count.unwrap_or(MAX_COUNT))?;
+            let span = count.checked_mul(step).ok_or("count * interval is out of range")?;
+            (ceil(to.unwrap_or(now))?.saturating_sub(span), count)
+        }
+    };
+    Ok((first, count))
+}
+
+fn generate(first: u64, count: u64, step: u64, now: u64) -> ApiRunePoolResponse {
+    let intervals: Vec<ApiInterval> = (0..count)
+        .map(|i| first + i * step)
+        .take_while(|&start| start < now)
+        .map(|start| ApiInterval {
+            start_time: start,
+            end_time: start + step,
+            count: count_at(start + step),
+            units: units_at(start + step),
+        })
+        .collect();
+    let end = intervals.last().map_or(first, |i| i.end_time);
+    ApiRunePoolResponse {
+        meta: ApiMeta {
+            start_time: first,
+            end_time: end,
+            start_count: count_at(first),
+            end_count: count_at(end),
+            start_units: units_at(first),
+            end_units: units_at(end),
+        },
+        intervals,
+    }
+}
+
+/// The fixture's intervals inside `[first, first + count * step)`, with meta rebuilt to match.
+fn from_fixture(fixture: &ApiRunePoolResponse, first: u64, count: u64, step: u64) -> ApiRunePoolResponse {
+    let end = first + count * step;
+    let selected: Vec<usize> = (0..fixture.intervals.len())
+        .filter(|&i| (first..end).contains(&fixture.intervals[i].start_time))
+        .collect();
+    let intervals: Vec<ApiInterval> = selected.iter().map(|&i| fixture.intervals[i].clone()).collect();
+
+    let (start_count, start_units) = match selected.first() {
+        Some(&i) if i > 0 => (fixture.intervals[i - 1].count, fixture.intervals[i - 1].units),
+        _ => (fixture.meta.start_count, fixture.meta.start_units),
+    };
+    let meta = match (intervals.first(), intervals.last()) {
+        (Some(head), Some(tail)) => ApiMeta {
+            start_time: head.start_time,
+            end_time: tail.end_time,
+            start_count,
+            end_count: tail.count,
+            start_units,
+            end_units: tail.units,
+        },
+        _ => ApiMeta { start_time: first, end_time: end, start_count, end_count: start_count, start_units, end_units: start_units },
+    };
+    ApiRunePoolResponse { meta, intervals }
+}
+
+fn bad_request(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response()
+}
+
+async fn runepool_history(
+    State(mock): State<Arc<MockState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let request = mock.requests.fetch_add(1, Ordering::SeqCst) + 1;
+    if !mock.options.latency.is_zero() {
+        tokio::time::sleep(mock.options.latency).await;
+    }
+
+    match mock.options.error_mode {
+        ErrorMode::Status(code) => {
+            let status = StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            return (status, Json(json!({ "error": "mock failure" }))).into_response();
+        }
+        ErrorMode::Flaky(every) if request % every == 0 => {
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "error": "mock flake" }))).into_response();
+        }
+        ErrorMode::Malformed => return (StatusCode::OK, "{\"meta\":{\"startTime\":").into_response(),
+        _ => {}
+    }
+
+    let Some(step) = params.get("interval").and_then(|name| interval_seconds(name)) else {
+        return bad_request("'interval' must be one of 5min, hour, day, week, month, quarter, year".to_string());
+    };
+    let now = chrono::Utc::now().timestamp() as u64;
+    let (first, count) = match select_buckets(&params, step, now) {
+        Ok(selection) => selection,
+        Err(message) => return bad_request(message),
+    };
+
+    let mut body = match &mock.options.fixture {
+        Some(fixture) => from_fixture(fixture, first, count, step),
+        None => generate(first, count, step, now),
+    };
+    if mock.options.error_mode == ErrorMode::Inconsistent {
+        body.meta.end_count += 1;
+    }
+    Json(body).into_response()
+}
+
+pub fn router(options: MockOptions) -> Router {
+    let state = Arc::new(MockState { options, requests: AtomicU64::new(0) });
+    Router::new()
+        .route("/v2/history/runepool", get(runepool_history))
+        .with_state(state)
+}
+
+/// Runs the mock on `HOST:MOCK_MIDGARD_PORT` until the process is stopped.
+pub async fn serve(config: &Config) -> Result<(), Box<dyn Error>> {
+    let options = MockOptions::from_config(config)?;
+    let addr = format!("{}:{}", config.host, config.mock_midgard_port);
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    println!("Mock Midgard running at http://{}/v2/history/runepool", addr);
+    axum::serve(listener, router(options)).await?;
+    Ok(())
+}
+
+/// Starts the mock on an ephemeral local port and returns its base URL.
+#[cfg(test)]
+pub async fn spawn(options: MockOptions) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router(options)).await });
+    format!("http://{}/v2/history/runepool", addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::validate::validate;
+
+    async fn fetch(url: &str, query: &str) -> reqwest::Response {
+        reqwest::get(format!("{}?{}", url, query)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn honors_interval_from_and_count() {
+        let url = spawn(MockOptions::default()).await;
+        let body: ApiRunePoolResponse = fetch(&url, "interval=hour&from=1700000123&count=24").await.json().await.unwrap();
+
+        assert_eq!(body.intervals.len(), 24);
+        assert_eq!(body.intervals[0].start_time, 1_699_999_200);
+        assert!(body.intervals.iter().all(|i| i.end_time - i.start_time == 3_600));
+        assert!(validate(&body).is_empty());
+
+        let again: ApiRunePoolResponse = fetch(&url, "interval=hour&from=1700000123&count=24").await.json().await.unwrap();
+        assert_eq!(serde_json::to_value(&again).unwrap(), serde_json::to_value(&body).unwrap());
+
+        let range: ApiRunePoolResponse = fetch(&url, "interval=day&from=1700000000&to=1700864000").await.json().await.unwrap();
+        assert_eq!(range.intervals.len(), 11);
+        assert_eq!(fetch(&url, "interval=hour&count=401").await.status(), StatusCode::BAD_REQUEST);
+        for query in ["interval=year&count=18446744073709551615", "interval=day&to=18446744073709551615&count=2"] {
+            assert_eq!(fetch(&url, query).await.status(), StatusCode::BAD_REQUEST, "{}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn filters_fixture_by_range() {
+        let fixture = generate(1_700_000_000 / 3_600 * 3_600, 48, 3_600, u64::MAX);
+        let url = spawn(MockOptions { fixture: Some(fixture.clone()), ..Default::default() }).await;
+        let from = fixture.intervals[10].start_time;
+        let body: ApiRunePoolResponse =
+            fetch(&url, &format!("interval=hour&from={}&count=5", from)).await.json().await.unwrap();
+
+        assert_eq!(body.intervals.len(), 5);
+        assert_eq!(body.intervals[0].start_time, from);
+        assert_eq!(body.meta.start_count, fixture.intervals[9].count);
+        assert!(validate(&body).is_empty());
+    }
+
+    #[tokio::test]
+    async fn injects_errors() {
+        let flaky = spawn(MockOptions { error_mode: ErrorMode::Flaky(2), ..Default::default() }).await;
+        assert_eq!(fetch(&flaky, "interval=hour&count=1").await.status(), StatusCode::OK);
+        assert_eq!(fetch(&flaky, "interval=hour&count=1").await.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let malformed = spawn(MockOptions { error_mode: ErrorMode::Malformed, ..Default::default() }).await;
+        assert!(fetch(&malformed, "interval=hour&count=1").await.json::<ApiRunePoolResponse>().await.is_err());
+
+        let inconsistent = spawn(MockOptions { error_mode: ErrorMode::Inconsistent, ..Default::default() }).await;
+        let body: ApiRunePoolResponse = fetch(&inconsistent, "interval=hour&count=3").await.json().await.unwrap();
+        assert!(!validate(&body).is_empty());
+    }
+}
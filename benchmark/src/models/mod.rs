@@ -1 +1,4 @@
 pub mod rune_pool;
+pub mod series;
+pub mod validate;
+pub mod verify;
@@ -46,7 +46,7 @@ pub struct ApiRunePoolResponse {
     pub intervals: Vec<ApiInterval>,
 }
 
-#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, FromRow)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize, FromRow)]
 pub struct DbMeta {
     pub start_time: u64,
     pub end_time: u64,
@@ -56,7 +56,7 @@ pub struct DbMeta {
     pub end_units: u64,
 }
 
-#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, FromRow)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize, FromRow)]
 pub struct DbInterval {
     pub start_time: u64,
     pub end_time: u64,
@@ -64,12 +64,46 @@ pub struct DbInterval {
     pub units: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DbRunePoolResponse {
     pub meta: DbMeta,
     pub intervals: Vec<DbInterval>,
 }
 
+impl DbRunePoolResponse {
+    /// Merges sorted intervals into `seconds`-wide buckets. `count` and `units` are
+    /// gauges, so each bucket keeps the values of its last interval.
+    pub fn resample(&self, seconds: u64) -> DbRunePoolResponse {
+        let mut intervals: Vec<DbInterval> = Vec::new();
+        for interval in &self.intervals {
+            match intervals.last_mut() {
+                Some(last) if last.start_time / seconds == interval.start_time / seconds => {
+                    last.end_time = interval.end_time;
+                    last.count = interval.count;
+                    last.units = interval.units;
+                }
+                _ => intervals.push(interval.clone()),
+            }
+        }
+        DbRunePoolResponse {
+            meta: self.meta.clone(),
+            intervals,
+        }
+    }
+
+    /// Intervals with `from <= start_time < to`.
+    pub fn range(&self, from: u64, to: u64) -> DbRunePoolResponse {
+        DbRunePoolResponse {
+            meta: self.meta.clone(),
+            intervals: self
+                .intervals
+                .iter()
+                .filter(|i| i.start_time >= from && i.start_time < to)
+                .cloned()
+                .collect(),
+        }
+    }
+}
 
 impl From<ApiRunePoolResponse> for DbRunePoolResponse {
     fn from(api: ApiRunePoolResponse) -> Self {
@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map};
+use std::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// Integer amounts and counts (1e8-scaled RUNE and asset values).
+    U64,
+    /// Prices and ratios.
+    F64,
+}
+
+/// A named numeric column of a series.
+#[derive(Debug)]
+pub struct Field {
+    /// Column, field or key name in the stores.
+    pub name: &'static str,
+    /// Key in Midgard's JSON, where every number is a string.
+    pub api_name: &'static str,
+    pub kind: FieldKind,
+}
+
+impl Field {
+    const fn u64(name: &'static str, api_name: &'static str) -> Self {
+        Field { name, api_name, kind: FieldKind::U64 }
+    }
+
+    const fn f64(name: &'static str, api_name: &'static str) -> Self {
+        Field { name, api_name, kind: FieldKind::F64 }
+    }
+}
+
+/// Describes one Midgard history series: where it is fetched from, where it is stored and
+/// which numeric fields each interval carries. Adding a series is adding one of these to `ALL`.
+#[derive(Debug)]
+pub struct Schema {
+    pub name: &'static str,
+    /// Table, collection or key prefix; every one starts with `history_`.
+    pub table: &'static str,
+    /// Path below the Midgard base URL; `{pool}` makes the series per-pool.
+    pub path: &'static str,
+    pub fields: &'static [Field],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    U64(u64),
+    F64(f64),
+}
+
+impl Value {
+    /// The value as 8 bytes' worth: the integer itself, or the float's IEEE 754 bits.
+    pub fn to_bits(self) -> u64 {
+        match self {
+            Value::U64(n) => n,
+            Value::F64(x) => x.to_bits(),
+        }
+    }
+
+    pub fn from_bits(kind: FieldKind, bits: u64) -> Self {
+        match kind {
+            FieldKind::U64 => Value::U64(bits),
+            FieldKind::F64 => Value::F64(f64::from_bits(bits)),
+        }
+    }
+}
+
+/// One interval of any series: the time span plus one value per schema field, in field order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub values: Vec<Value>,
+}
+
+pub static DEPTHS: Schema = Schema {
+    name: "depths",
+    table: "history_depths",
+    path: "history/depths/{pool}",
+    fields: &[
+        Field::u64("asset_depth", "assetDepth"),
+        Field::u64("rune_depth", "runeDepth"),
+        Field::f64("asset_price", "assetPrice"),
+        Field::f64("asset_price_usd", "assetPriceUSD"),
+        Field::u64("liquidity_units", "liquidityUnits"),
+        Field::u64("members_count", "membersCount"),
+        Field::u64("synth_units", "synthUnits"),
+        Field::u64("synth_supply", "synthSupply"),
+        Field::u64("units", "units"),
+        Field::f64("luvi", "luvi"),
+    ],
+};
+
+pub static EARNINGS: Schema = Schema {
+    name: "earnings",
+    table: "history_earnings",
+    path: "history/earnings",
+    fields: &[
+        Field::u64("liquidity_fees", "liquidityFees"),
+        Field::u64("block_rewards", "blockRewards"),
+        Field::u64("earnings", "earnings"),
+        Field::u64("bonding_earnings", "bondingEarnings"),
+        Field::u64("liquidity_earnings", "liquidityEarnings"),
+        Field::f64("avg_node_count", "avgNodeCount"),
+        Field::f64("rune_price_usd", "runePriceUSD"),
+    ],
+};
+
+pub static SWAPS: Schema = Schema {
+    name: "swaps",
+    table: "history_swaps",
+    path: "history/swaps",
+    fields: &[
+        Field::u64("to_asset_count", "toAssetCount"),
+        Field::u64("to_rune_count", "toRuneCount"),
+        Field::u64("synth_mint_count", "synthMintCount"),
+        Field::u64("synth_redeem_count", "synthRedeemCount"),
+        Field::u64("total_count", "totalCount"),
+        Field::u64("to_asset_volume", "toAssetVolume"),
+        Field::u64("to_rune_volume", "toRuneVolume"),
+        Field::u64("synth_mint_volume", "synthMintVolume"),
+        Field::u64("synth_redeem_volume", "synthRedeemVolume"),
+        Field::u64("total_volume", "totalVolume"),
+        Field::u64("total_fees", "totalFees"),
+        Field::f64("average_slip", "averageSlip"),
+        Field::f64("rune_price_usd", "runePriceUSD"),
+    ],
+};
+
+pub static TVL: Schema = Schema {
+    name: "tvl",
+    table: "history_tvl",
+    path: "history/tvl",
+    fields: &[
+        Field::u64("total_value_pooled", "totalValuePooled"),
+        Field::u64("total_value_bonded", "totalValueBonded"),
+        Field::u64("total_value_locked", "totalValueLocked"),
+        Field::f64("rune_price_usd", "runePriceUSD"),
+    ],
+};
+
+/// Every series the stores create tables for. RUNEPool history is not one of them: it keeps
+/// its typed meta and intervals, stored per dataset.
+pub static ALL: [&Schema; 4] = [&DEPTHS, &EARNINGS, &SWAPS, &TVL];
+
+/// Table of the former `runepool` series, which only duplicated the RUNEPool intervals.
+/// Stores that create tables at startup drop it.
+pub const RETIRED_TABLE: &str = "history_runepool";
+
+pub fn find(name: &str) -> Result<&'static Schema, Box<dyn Error>> {
+    ALL.iter()
+        .copied()
+        .find(|schema| schema.name == name)
+        .ok_or_else(|| format!("Unknown history series: {}", name).into())
+}
+
+/// Pools are chain and asset names like `BTC.BTC` or `ETH.USDT-0XDAC17F958D2EE523A2206206994597C13D831EC7`.
+/// The name goes into Midgard URLs, store keys and file names, so it is kept to that alphabet
+/// and must hold a letter or digit, which rules out `.` and `..`.
+pub fn check_pool(pool: &str) -> Result<(), Box<dyn Error>> {
+    let valid = pool.len() <= 128
+        && pool.bytes().any(|b| b.is_ascii_alphanumeric())
+        && pool.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-');
+    if !valid {
+        return Err(format!("Invalid pool '{}': use up to 128 letters, digits, '.' or '-'", pool).into());
+    }
+    Ok(())
+}
+
+/// Percent-encodes everything outside RFC 3986's unreserved characters.
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for b in segment.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+/// Midgard sends numbers as strings; plain JSON numbers are accepted too.
+fn number<T: std::str::FromStr>(interval: &serde_json::Value, key: &str) -> Result<T, Box<dyn Error>> {
+    let value = interval.get(key).ok_or_else(|| format!("Missing field {}", key))?;
+    let text = match value {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Number(n) => n.to_string(),
+        other => return Err(format!("Field {} is not a number: {}", key, other).into()),
+    };
+    text.parse().map_err(|_| format!("Field {} is not a valid number: {}", key, text).into())
+}
+
+impl Schema {
+    pub fn needs_pool(&self) -> bool {
+        self.path.contains("{pool}")
+    }
+
+    /// The Midgard path, with `pool` percent-encoded as one path segment.
+    pub fn path(&self, pool: &str) -> String {
+        self.path.replace("{pool}", &encode_segment(pool))
+    }
+
+    /// Parses the `intervals` of a Midgard response. `meta` only aggregates the intervals,
+    /// so it isn't kept; fields not in the schema are dropped.
+    pub fn decode(&self, body: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+        let response: serde_json::Value = serde_json::from_str(body)?;
+        let intervals = response
+            .get("intervals")
+            .and_then(|intervals| intervals.as_array())
+            .ok_or("Response has no intervals array")?;
+        let mut records = Vec::with_capacity(intervals.len());
+        for (i, interval) in intervals.iter().enumerate() {
+            let record = self.decode_interval(interval).map_err(|e| format!("intervals[{}]: {}", i, e))?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    fn decode_interval(&self, interval: &serde_json::Value) -> Result<Record, Box<dyn Error>> {
+        let mut values = Vec::with_capacity(self.fields.len());
+        for field in self.fields {
+            values.push(match field.kind {
+                FieldKind::U64 => Value::U64(number(interval, field.api_name)?),
+                FieldKind::F64 => {
+                    // SurrealDB stores NaN as null and SQLite's REAL NOT NULL rejects it, so
+                    // they are refused here rather than at write time.
+                    let x: f64 = number(interval, field.api_name)?;
+                    if !x.is_finite() {
+                        return Err(format!("Field {} is not finite: {}", field.api_name, x).into());
+                    }
+                    Value::F64(x)
+                }
+            });
+        }
+        Ok(Record {
+            start_time: number(interval, "startTime")?,
+            end_time: number(interval, "endTime")?,
+            values,
+        })
+    }
+
+    /// A record in Midgard's JSON shape, numbers as strings.
+    pub fn to_json(&self, record: &Record) -> serde_json::Value {
+        let mut interval = Map::new();
+        interval.insert("startTime".to_string(), json!(record.start_time.to_string()));
+        interval.insert("endTime".to_string(), json!(record.end_time.to_string()));
+        for (field, value) in self.fields.iter().zip(&record.values) {
+            let text = match value {
+                Value::U64(n) => n.to_string(),
+                Value::F64(x) => x.to_string(),
+            };
+            interval.insert(field.api_name.to_string(), json!(text));
+        }
+        serde_json::Value::Object(interval)
+    }
+
+    /// Checks a record read back from a store has one value of the right kind per field.
+    pub fn check(&self, record: &Record) -> Result<(), Box<dyn Error>> {
+        let matches = record.values.len() == self.fields.len()
+            && self.fields.iter().zip(&record.values).all(|(field, value)| {
+                matches!((field.kind, value), (FieldKind::U64, Value::U64(_)) | (FieldKind::F64, Value::F64(_)))
+            });
+        if !matches {
+            return Err(format!("Stored {} record does not match the schema", self.name).into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_midgard_payloads() {
+        let body = r#"{
+            "meta": {"startTime": "0", "endTime": "7200"},
+            "intervals": [
+                {"startTime": "0", "endTime": "3600", "totalValuePooled": "10", "totalValueBonded": "20",
+                 "totalValueLocked": "30", "runePriceUSD": "1.25", "poolsDepth": []},
+                {"startTime": "3600", "endTime": "7200", "totalValuePooled": "11", "totalValueBonded": "21",
+                 "totalValueLocked": "32", "runePriceUSD": "1.5"}
+            ]
+        }"#;
+        let records = TVL.decode(body).unwrap();
+        assert_eq!(
+            records[1],
+            Record {
+                start_time: 3600,
+                end_time: 7200,
+                values: vec![Value::U64(11), Value::U64(21), Value::U64(32), Value::F64(1.5)],
+            }
+        );
+        let reencoded = json!({ "intervals": [TVL.to_json(&records[1])] }).to_string();
+        assert_eq!(TVL.decode(&reencoded).unwrap()[0], records[1]);
+
+        // The same body lacks every depths field.
+        let error = DEPTHS.decode(body).unwrap_err().to_string();
+        assert_eq!(error, "intervals[0]: Missing field assetDepth");
+    }
+
+    #[test]
+    fn rejects_non_finite_floats() {
+        for price in ["NaN", "inf", "-infinity"] {
+            let body = json!({ "intervals": [{
+                "startTime": "0", "endTime": "3600", "totalValuePooled": "10", "totalValueBonded": "20",
+                "totalValueLocked": "30", "runePriceUSD": price
+            }] });
+            let error = TVL.decode(&body.to_string()).unwrap_err().to_string();
+            assert!(error.starts_with("intervals[0]: Field runePriceUSD is not finite"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn pools_stay_within_one_path_segment() {
+        for pool in ["BTC.BTC", "ETH.USDT-0XDAC17F958D2EE523A2206206994597C13D831EC7"] {
+            assert!(check_pool(pool).is_ok(), "{}", pool);
+            assert_eq!(DEPTHS.path(pool), format!("history/depths/{}", pool));
+        }
+        for pool in ["", ".", "..", "../../v2/health", "BTC/BTC", "BTC BTC", "BTC%2FBTC", "BTC_BTC"] {
+            assert!(check_pool(pool).is_err(), "{}", pool);
+        }
+        assert_eq!(DEPTHS.path("a/../b?c"), "history/depths/a%2F..%2Fb%3Fc");
+    }
+}
@@ -0,0 +1,196 @@
+use crate::models::rune_pool::ApiRunePoolResponse;
+use crate::models::series::{Record, Schema, Value};
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    /// Location of the offending value, e.g. `intervals[3].endTime`.
+    pub path: String,
+    pub message: String,
+}
+
+fn violation(path: impl Into<String>, message: impl Into<String>) -> Violation {
+    Violation { path: path.into(), message: message.into() }
+}
+
+/// Largest integer every backend can store: the SQL stores, SurrealDB and MongoDB only
+/// have signed 64-bit integers, so anything above `i64::MAX` is refused before any write.
+const MAX_INTEGER: u64 = i64::MAX as u64;
+
+fn check_integer(path: String, value: u64, violations: &mut Vec<Violation>) {
+    if value > MAX_INTEGER {
+        violations.push(violation(path, format!("{} exceeds the largest storable integer {}", value, MAX_INTEGER)));
+    }
+}
+
+/// Each interval must end after it starts, and each must start exactly where the previous one ended.
+fn check_spans(spans: &[(u64, u64)], violations: &mut Vec<Violation>) {
+    for (i, &(start_time, end_time)) in spans.iter().enumerate() {
+        if start_time >= end_time {
+            violations.push(violation(
+                format!("intervals[{}].endTime", i),
+                format!("endTime {} must be after startTime {}", end_time, start_time),
+            ));
+        }
+    }
+
+    for (i, pair) in spans.windows(2).enumerate() {
+        let ((prev_start, prev_end), (next_start, _)) = (pair[0], pair[1]);
+        let path = format!("intervals[{}].startTime", i + 1);
+        if next_start <= prev_start {
+            violations.push(violation(
+                path,
+                format!("startTime {} is not after the previous interval's {}", next_start, prev_start),
+            ));
+        } else if next_start < prev_end {
+            violations.push(violation(
+                path,
+                format!("startTime {} overlaps the previous interval ending at {}", next_start, prev_end),
+            ));
+        } else if next_start > prev_end {
+            violations.push(violation(
+                path,
+                format!("gap between the previous interval ending at {} and startTime {}", prev_end, next_start),
+            ));
+        }
+    }
+}
+
+/// Checks a Midgard RUNEPool history payload for internal consistency. Interval counts and
+/// units are end-of-interval values, so only the meta `end*` fields can be checked against them.
+pub fn validate(payload: &ApiRunePoolResponse) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let meta = &payload.meta;
+
+    if meta.start_time >= meta.end_time {
+        violations.push(violation(
+            "meta.endTime",
+            format!("endTime {} must be after startTime {}", meta.end_time, meta.start_time),
+        ));
+    }
+
+    let meta_fields = [
+        ("startTime", meta.start_time),
+        ("endTime", meta.end_time),
+        ("startCount", meta.start_count),
+        ("endCount", meta.end_count),
+        ("startUnits", meta.start_units),
+        ("endUnits", meta.end_units),
+    ];
+    for (name, value) in meta_fields {
+        check_integer(format!("meta.{}", name), value, &mut violations);
+    }
+    for (i, interval) in payload.intervals.iter().enumerate() {
+        let fields = [
+            ("startTime", interval.start_time),
+            ("endTime", interval.end_time),
+            ("count", interval.count),
+            ("units", interval.units),
+        ];
+        for (name, value) in fields {
+            check_integer(format!("intervals[{}].{}", i, name), value, &mut violations);
+        }
+    }
+
+    let spans: Vec<(u64, u64)> = payload.intervals.iter().map(|i| (i.start_time, i.end_time)).collect();
+    check_spans(&spans, &mut violations);
+
+    if let (Some(first), Some(last)) = (payload.intervals.first(), payload.intervals.last()) {
+        if meta.start_time != first.start_time {
+            violations.push(violation(
+                "meta.startTime",
+                format!("startTime {} does not match the first interval's {}", meta.start_time, first.start_time),
+            ));
+        }
+        if meta.end_time != last.end_time {
+            violations.push(violation(
+                "meta.endTime",
+                format!("endTime {} does not match the last interval's {}", meta.end_time, last.end_time),
+            ));
+        }
+        if meta.end_count != last.count {
+            violations.push(violation(
+                "meta.endCount",
+                format!("endCount {} does not match the last interval's count {}", meta.end_count, last.count),
+            ));
+        }
+        if meta.end_units != last.units {
+            violations.push(violation(
+                "meta.endUnits",
+                format!("endUnits {} does not match the last interval's units {}", meta.end_units, last.units),
+            ));
+        }
+    }
+
+    violations
+}
+
+/// Checks the intervals of any other history series; those have no meta worth checking.
+pub fn validate_records(schema: &Schema, records: &[Record]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        check_integer(format!("intervals[{}].startTime", i), record.start_time, &mut violations);
+        check_integer(format!("intervals[{}].endTime", i), record.end_time, &mut violations);
+        for (field, value) in schema.fields.iter().zip(&record.values) {
+            if let Value::U64(n) = *value {
+                check_integer(format!("intervals[{}].{}", i, field.api_name), n, &mut violations);
+            }
+        }
+    }
+    let spans: Vec<(u64, u64)> = records.iter().map(|r| (r.start_time, r.end_time)).collect();
+    check_spans(&spans, &mut violations);
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::rune_pool::{ApiInterval, ApiMeta};
+    use crate::db::fixtures;
+    use crate::models::series::SWAPS;
+
+    fn payload() -> ApiRunePoolResponse {
+        let intervals: Vec<ApiInterval> = (0..4)
+            .map(|i| ApiInterval { start_time: i * 3600, end_time: (i + 1) * 3600, count: 10 + i, units: 100 + i })
+            .collect();
+        ApiRunePoolResponse {
+            meta: ApiMeta { start_time: 0, end_time: 4 * 3600, start_count: 9, end_count: 13, start_units: 99, end_units: 103 },
+            intervals,
+        }
+    }
+
+    #[test]
+    fn accepts_consistent_payload() {
+        assert!(validate(&payload()).is_empty());
+    }
+
+    #[test]
+    fn lists_every_violation() {
+        let mut bad = payload();
+        bad.intervals[1].end_time = bad.intervals[1].start_time;
+        bad.intervals[3].start_time = 3 * 3600 + 60;
+        bad.meta.end_count = 99;
+
+        let paths: Vec<String> = validate(&bad).into_iter().map(|v| v.path).collect();
+        assert_eq!(
+            paths,
+            vec!["intervals[1].endTime", "intervals[2].startTime", "intervals[3].startTime", "meta.endCount"]
+        );
+    }
+
+    #[test]
+    fn rejects_integers_past_i64() {
+        let mut bad = payload();
+        bad.meta.end_units = i64::MAX as u64 + 1;
+        bad.intervals[3].units = i64::MAX as u64 + 1;
+
+        let paths: Vec<String> = validate(&bad).into_iter().map(|v| v.path).collect();
+        assert_eq!(paths, vec!["meta.endUnits", "intervals[3].units"]);
+        assert!(validate_records(&SWAPS, &fixtures::extreme_records(i64::MAX as u64)).is_empty());
+        let paths: Vec<String> = validate_records(&SWAPS, &fixtures::extreme_records(i64::MAX as u64 + 1))
+            .into_iter()
+            .map(|v| v.path)
+            .collect();
+        assert_eq!(paths, vec!["intervals[0].toAssetCount", "intervals[1].toAssetCount"]);
+    }
+}
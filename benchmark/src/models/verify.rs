@@ -0,0 +1,119 @@
+use crate::models::rune_pool::{DbInterval, DbMeta, DbRunePoolResponse};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldMismatch {
+    pub field: &'static str,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IntervalMismatch {
+    pub start_time: u64,
+    pub fields: Vec<FieldMismatch>,
+}
+
+/// Differences between a backend's dataset and the reference, keyed by interval `start_time`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Discrepancies {
+    pub meta: Vec<FieldMismatch>,
+    pub missing: Vec<u64>,
+    pub extra: Vec<u64>,
+    pub mismatched: Vec<IntervalMismatch>,
+}
+
+impl Discrepancies {
+    pub fn is_consistent(&self) -> bool {
+        self.meta.is_empty() && self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+fn meta_fields(meta: &DbMeta) -> [(&'static str, u64); 6] {
+    [
+        ("start_time", meta.start_time),
+        ("end_time", meta.end_time),
+        ("start_count", meta.start_count),
+        ("end_count", meta.end_count),
+        ("start_units", meta.start_units),
+        ("end_units", meta.end_units),
+    ]
+}
+
+fn interval_fields(interval: &DbInterval) -> [(&'static str, u64); 3] {
+    [("end_time", interval.end_time), ("count", interval.count), ("units", interval.units)]
+}
+
+fn mismatches<const N: usize>(expected: [(&'static str, u64); N], actual: [(&'static str, u64); N]) -> Vec<FieldMismatch> {
+    expected
+        .into_iter()
+        .zip(actual)
+        .filter(|((_, e), (_, a))| e != a)
+        .map(|((field, expected), (_, actual))| FieldMismatch { field, expected, actual })
+        .collect()
+}
+
+/// Compares `actual` against `expected` field by field. A `start_time` stored more than
+/// once counts as extra for every copy after the first.
+pub fn compare(expected: &DbRunePoolResponse, actual: &DbRunePoolResponse) -> Discrepancies {
+    let mut report = Discrepancies {
+        meta: mismatches(meta_fields(&expected.meta), meta_fields(&actual.meta)),
+        ..Default::default()
+    };
+
+    let mut remaining: BTreeMap<u64, &DbInterval> = expected.intervals.iter().map(|i| (i.start_time, i)).collect();
+    for interval in &actual.intervals {
+        match remaining.remove(&interval.start_time) {
+            Some(reference) => {
+                let fields = mismatches(interval_fields(reference), interval_fields(interval));
+                if !fields.is_empty() {
+                    report.mismatched.push(IntervalMismatch { start_time: interval.start_time, fields });
+                }
+            }
+            None => report.extra.push(interval.start_time),
+        }
+    }
+    report.missing = remaining.into_keys().collect();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(start_time: u64, count: u64) -> DbInterval {
+        DbInterval { start_time, end_time: start_time + 3600, count, units: count * 10 }
+    }
+
+    fn dataset(intervals: Vec<DbInterval>) -> DbRunePoolResponse {
+        DbRunePoolResponse {
+            meta: DbMeta { start_time: 0, end_time: 7200, start_count: 1, end_count: 2, start_units: 10, end_units: 20 },
+            intervals,
+        }
+    }
+
+    #[test]
+    fn reports_missing_extra_and_mismatched_intervals() {
+        let expected = dataset(vec![interval(0, 1), interval(3600, 2), interval(7200, 3)]);
+        let mut actual = dataset(vec![interval(0, 1), interval(3600, 5), interval(10800, 4), interval(0, 1)]);
+        actual.meta.end_count = 9;
+
+        let report = compare(&expected, &actual);
+        assert!(!report.is_consistent());
+        assert_eq!(report.meta, vec![FieldMismatch { field: "end_count", expected: 2, actual: 9 }]);
+        assert_eq!(report.missing, vec![7200]);
+        assert_eq!(report.extra, vec![10800, 0]);
+        assert_eq!(report.mismatched.len(), 1);
+        assert_eq!(report.mismatched[0].start_time, 3600);
+        assert_eq!(
+            report.mismatched[0].fields,
+            vec![
+                FieldMismatch { field: "count", expected: 2, actual: 5 },
+                FieldMismatch { field: "units", expected: 20, actual: 50 },
+            ]
+        );
+
+        assert!(compare(&expected, &expected).is_consistent());
+    }
+}